@@ -3,11 +3,11 @@ use redox::{cmp, env};
 use redox::collections::BTreeMap;
 use redox::fs::{self, File};
 use redox::io::{Read, Seek, SeekFrom};
-use redox::time::{self, Duration};
+use redox::time::SystemClock;
 use redox::vec::Vec;
 use redox::string::{String, ToString};
 
-use orbital::{event, BmpFile, Color, EventOption, MouseEvent, Window};
+use orbital::{event, BmpFile, Color, DoubleClick, EventOption, Window};
 
 struct FileType {
     description: &'static str,
@@ -113,8 +113,7 @@ pub struct FileManager {
     files: Vec<String>,
     file_sizes: Vec<String>,
     selected: isize,
-    last_mouse_event: MouseEvent,
-    click_time: Duration,
+    double_click: DoubleClick,
     window: Box<Window>,
 }
 
@@ -133,14 +132,7 @@ impl FileManager {
             files: Vec::new(),
             file_sizes: Vec::new(),
             selected: -1,
-            last_mouse_event: MouseEvent {
-                x: 0,
-                y: 0,
-                left_button: false,
-                middle_button: false,
-                right_button: false,
-            },
-            click_time: Duration::new(0, 0),
+            double_click: DoubleClick::new(),
             window: Window::new(-1,-1,0,0,"").unwrap(),
         }
     }
@@ -405,11 +397,7 @@ impl FileManager {
 
                     //Check for double click
                     if mouse_event.left_button {
-                        let click_time = Duration::realtime();
-
-                        if click_time - self.click_time < Duration::new(0, 500 * time::NANOS_PER_MILLI)
-                            && self.last_mouse_event.x == mouse_event.x
-                            && self.last_mouse_event.y == mouse_event.y {
+                        if self.double_click.click(mouse_event.x, mouse_event.y, &SystemClock) {
                             if self.selected >= 0 && self.selected < self.files.len() as isize {
                                 if let Some(file) = self.files.get(self.selected as usize) {
                                     if file.ends_with('/') {
@@ -419,12 +407,8 @@ impl FileManager {
                                     }
                                 }
                             }
-                            self.click_time = Duration::new(0, 0);
-                        } else {
-                            self.click_time = click_time;
                         }
                     }
-                    self.last_mouse_event = mouse_event;
 
                     if command.is_none() && redraw {
                         command = Some(FileManagerCommand::Redraw);
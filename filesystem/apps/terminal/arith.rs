@@ -0,0 +1,225 @@
+use redox::iter::Peekable;
+use redox::str::Chars;
+use redox::string::{String, ToString};
+
+/// Evaluate an integer expression, as in `$((expr))`
+/// The operators are `+`, `-`, `*`, `/` and `%`, with the usual precedence, unary `+` and `-`,
+/// and parentheses. Variables are named with or without a `$`, and an unset one is 0
+pub fn eval<F: Fn(&str) -> Option<String>>(expr: &str, get_var: &F) -> Result<isize, String> {
+    let mut parser = Parser {
+        chars: expr.chars().peekable(),
+        get_var: get_var,
+    };
+
+    let value = try!(parser.sum());
+    parser.skip_spaces();
+    match parser.chars.next() {
+        Some(c) => Err(format!("Unexpected {} in arithmetic", c)),
+        None => Ok(value),
+    }
+}
+
+/// A recursive descent parser, evaluating as it goes
+struct Parser<'a, F: 'a> {
+    chars: Peekable<Chars<'a>>,
+    get_var: &'a F,
+}
+
+impl<'a, F: Fn(&str) -> Option<String>> Parser<'a, F> {
+    fn skip_spaces(&mut self) {
+        while self.chars.peek().map_or(false, |&c| c == ' ' || c == '\t') {
+            self.chars.next();
+        }
+    }
+
+    /// Terms added or subtracted
+    fn sum(&mut self) -> Result<isize, String> {
+        let mut value = try!(self.product());
+        loop {
+            self.skip_spaces();
+            let op = match self.chars.peek() {
+                Some(&'+') => '+',
+                Some(&'-') => '-',
+                _ => return Ok(value),
+            };
+            self.chars.next();
+
+            let right = try!(self.product());
+            let result = if op == '+' {
+                value.checked_add(right)
+            } else {
+                value.checked_sub(right)
+            };
+            value = try!(result.ok_or("Arithmetic overflow".to_string()));
+        }
+    }
+
+    /// Factors multiplied, divided or taken the remainder of
+    fn product(&mut self) -> Result<isize, String> {
+        let mut value = try!(self.factor());
+        loop {
+            self.skip_spaces();
+            let op = match self.chars.peek() {
+                Some(&'*') => '*',
+                Some(&'/') => '/',
+                Some(&'%') => '%',
+                _ => return Ok(value),
+            };
+            self.chars.next();
+
+            let right = try!(self.factor());
+            if op != '*' && right == 0 {
+                return Err("Division by zero".to_string());
+            }
+            let result = match op {
+                '*' => value.checked_mul(right),
+                '/' => value.checked_div(right),
+                _ => value.checked_rem(right),
+            };
+            value = try!(result.ok_or("Arithmetic overflow".to_string()));
+        }
+    }
+
+    /// A number, a variable, a signed factor or an expression in parentheses
+    fn factor(&mut self) -> Result<isize, String> {
+        self.skip_spaces();
+        match self.chars.peek().map(|&c| c) {
+            Some('-') => {
+                self.chars.next();
+                let value = try!(self.factor());
+                value.checked_neg().ok_or("Arithmetic overflow".to_string())
+            }
+            Some('+') => {
+                self.chars.next();
+                self.factor()
+            }
+            Some('(') => {
+                self.chars.next();
+                let value = try!(self.sum());
+                self.skip_spaces();
+                match self.chars.next() {
+                    Some(')') => Ok(value),
+                    _ => Err("Missing ) in arithmetic".to_string()),
+                }
+            }
+            Some(c) if c >= '0' && c <= '9' => {
+                let digits = self.take_while(|c| c >= '0' && c <= '9');
+                parse_digits(&digits, false)
+            }
+            Some(c) if c == '$' || is_name_start(c) => {
+                if c == '$' {
+                    self.chars.next();
+                }
+                let name = self.take_while(|c| is_name_start(c) || (c >= '0' && c <= '9'));
+                if name.is_empty() {
+                    return Err("Missing variable name after $ in arithmetic".to_string());
+                }
+                match (self.get_var)(&name) {
+                    Some(value) => number(value.trim()),
+                    None => Ok(0),
+                }
+            }
+            Some(c) => Err(format!("Unexpected {} in arithmetic", c)),
+            None => Err("Missing number in arithmetic".to_string()),
+        }
+    }
+
+    fn take_while<P: Fn(char) -> bool>(&mut self, predicate: P) -> String {
+        let mut taken = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !predicate(c) {
+                break;
+            }
+            taken.push(c);
+            self.chars.next();
+        }
+        taken
+    }
+}
+
+/// Can a variable name start with the character?
+fn is_name_start(c: char) -> bool {
+    (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_'
+}
+
+/// Parse an integer, giving an error if it is not one
+pub fn number(value: &str) -> Result<isize, String> {
+    let digits = if value.starts_with('-') {
+        &value[1 ..]
+    } else {
+        value
+    };
+    if digits.is_empty() || !digits.chars().all(|c| c >= '0' && c <= '9') {
+        return Err(format!("Not a number: {}", value));
+    }
+    parse_digits(digits, value.starts_with('-'))
+}
+
+/// The value of a string of decimal digits, negated if `negative` is set, giving an error
+/// if it does not fit
+fn parse_digits(digits: &str, negative: bool) -> Result<isize, String> {
+    let mut value: isize = 0;
+    for c in digits.chars() {
+        let digit = c as isize - '0' as isize;
+        let result = value.checked_mul(10).and_then(|value| if negative {
+            value.checked_sub(digit)
+        } else {
+            value.checked_add(digit)
+        });
+        value = try!(result.ok_or("Arithmetic overflow".to_string()));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use redox::string::{String, ToString};
+
+    fn vars(name: &str) -> Option<String> {
+        match name {
+            "x" => Some("5".to_string()),
+            "neg" => Some("-3".to_string()),
+            "word" => Some("five".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3", &vars), Ok(7));
+        assert_eq!(eval("(1 + 2) * 3", &vars), Ok(9));
+        assert_eq!(eval("10 - 4 - 3", &vars), Ok(3));
+        assert_eq!(eval("17 / 5 % 2", &vars), Ok(1));
+        assert_eq!(eval("-2 * -(3 + 1)", &vars), Ok(8));
+    }
+
+    #[test]
+    fn variables() {
+        assert_eq!(eval("x + 1", &vars), Ok(6));
+        assert_eq!(eval("$x * $neg", &vars), Ok(-15));
+        assert_eq!(eval("unset + 2", &vars), Ok(2));
+        assert!(eval("word + 1", &vars).is_err());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(eval("1 / 0", &vars).is_err());
+        assert!(eval("1 % (x - 5)", &vars).is_err());
+        assert!(eval("1 +", &vars).is_err());
+        assert!(eval("(1 + 2", &vars).is_err());
+        assert!(eval("1 2", &vars).is_err());
+        assert!(eval("", &vars).is_err());
+        assert!(eval("99999999999999999999", &vars).is_err());
+    }
+
+    #[test]
+    fn limits() {
+        let max = format!("{}", isize::max_value());
+        assert_eq!(eval(&max, &vars), Ok(isize::max_value()));
+        assert!(eval(&format!("{} + 1", max), &vars).is_err());
+        assert_eq!(number(&format!("{}", isize::min_value())), Ok(isize::min_value()));
+        assert!(number("99999999999999999999").is_err());
+        assert!(number("-99999999999999999999").is_err());
+    }
+}
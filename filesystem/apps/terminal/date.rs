@@ -0,0 +1,282 @@
+use redox::string::{String, ToString};
+use redox::time::Clock;
+use redox::vec::Vec;
+
+/// A date and time in UTC
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct DateTime {
+    pub year: i64,
+    /// The month, from 1 to 12
+    pub month: i64,
+    /// The day of the month, from 1
+    pub day: i64,
+    pub hour: i64,
+    pub minute: i64,
+    pub second: i64,
+}
+
+/// Is `year` a leap year?
+fn is_leap(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// The number of days in a month
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 => if is_leap(year) { 29 } else { 28 },
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since the Unix epoch of a date, from the civil calendar algorithm
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = (if y >= 0 { y } else { y - 399 }) / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+impl DateTime {
+    /// Convert seconds since the Unix epoch
+    pub fn from_timestamp(timestamp: i64) -> DateTime {
+        let days = (if timestamp >= 0 { timestamp } else { timestamp - 86399 }) / 86400;
+        let secs = timestamp - days * 86400;
+
+        let z = days + 719468;
+        let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        DateTime {
+            year: year,
+            month: month,
+            day: day,
+            hour: secs / 3600,
+            minute: secs / 60 % 60,
+            second: secs % 60,
+        }
+    }
+
+    /// The current time on `clock`
+    pub fn now(clock: &Clock) -> DateTime {
+        DateTime::from_timestamp(clock.realtime().secs)
+    }
+
+    /// Convert to seconds since the Unix epoch
+    pub fn timestamp(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * 86400 +
+            self.hour * 3600 + self.minute * 60 + self.second
+    }
+
+    /// The day of the year, from 1
+    pub fn day_of_year(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) - days_from_civil(self.year, 1, 1) + 1
+    }
+
+    /// Format with `%` specifiers:
+    /// `%Y` year, `%m` month, `%d` day, `%H` hour, `%M` minute, `%S` second,
+    /// `%j` day of the year, `%s` seconds since the epoch, `%F` as `%Y-%m-%d`,
+    /// `%T` as `%H:%M:%S` and `%%` a percent sign
+    pub fn format(&self, format: &str) -> Result<String, String> {
+        let mut output = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                output.push(c);
+                continue;
+            }
+
+            let part = match chars.next() {
+                Some('Y') => format!("{:04}", self.year),
+                Some('m') => format!("{:02}", self.month),
+                Some('d') => format!("{:02}", self.day),
+                Some('H') => format!("{:02}", self.hour),
+                Some('M') => format!("{:02}", self.minute),
+                Some('S') => format!("{:02}", self.second),
+                Some('j') => format!("{:03}", self.day_of_year()),
+                Some('s') => format!("{}", self.timestamp()),
+                Some('F') => format!("{:04}-{:02}-{:02}", self.year, self.month, self.day),
+                Some('T') => format!("{:02}:{:02}:{:02}", self.hour, self.minute, self.second),
+                Some('%') => "%".to_string(),
+                Some(other) => return Err(format!("Unknown format specifier: %{}", other)),
+                None => return Err("Format ends with a lone %".to_string()),
+            };
+            output.push_str(&part);
+        }
+        Ok(output)
+    }
+
+    /// Parse a `touch -t` timestamp, `[[CC]YY]MMDDhhmm[.ss]`
+    /// A missing year is taken from `now`, and a two digit year is in 1969 to 2068
+    pub fn parse_touch(timestamp: &str, now: &DateTime) -> Result<DateTime, String> {
+        let (digits, seconds) = match timestamp.find('.') {
+            Some(i) => (&timestamp[.. i], Some(&timestamp[i + 1 ..])),
+            None => (timestamp, None),
+        };
+
+        let invalid = || Err(format!("Invalid timestamp: {}, expected [[CC]YY]MMDDhhmm[.ss]", timestamp));
+
+        if !digits.chars().all(|c| c.is_digit(10)) ||
+           !seconds.map_or(true, |s| s.len() == 2 && s.chars().all(|c| c.is_digit(10))) {
+            return invalid();
+        }
+
+        let numbers: Vec<i64> = digits.as_bytes()
+                                      .chunks(2)
+                                      .map(|pair| pair.iter().fold(0, |n, &b| n * 10 + (b - b'0') as i64))
+                                      .collect();
+
+        let (year, rest) = match numbers.len() {
+            4 => (now.year, &numbers[..]),
+            5 => (if numbers[0] < 69 { 2000 + numbers[0] } else { 1900 + numbers[0] }, &numbers[1 ..]),
+            6 => (numbers[0] * 100 + numbers[1], &numbers[2 ..]),
+            _ => return invalid(),
+        };
+
+        let date = DateTime {
+            year: year,
+            month: rest[0],
+            day: rest[1],
+            hour: rest[2],
+            minute: rest[3],
+            second: seconds.map_or(0, |s| s.as_bytes().iter().fold(0, |n, &b| n * 10 + (b - b'0') as i64)),
+        };
+
+        if date.month < 1 || date.month > 12 || date.day < 1 ||
+           date.day > days_in_month(date.year, date.month) || date.hour > 23 ||
+           date.minute > 59 || date.second > 60 {
+            return invalid();
+        }
+
+        Ok(date)
+    }
+}
+
+/// Parse a duration such as `2`, `0.5s` or `100ms`, in seconds if there is no unit
+/// Returns the whole seconds and the nanoseconds
+pub fn parse_duration(text: &str) -> Result<(i64, i32), String> {
+    let invalid = || Err(format!("Invalid duration: {}", text));
+
+    let (number, nanos_per_unit) = if text.ends_with("ms") {
+        (&text[.. text.len() - 2], 1000000)
+    } else if text.ends_with('s') {
+        (&text[.. text.len() - 1], 1000000000)
+    } else {
+        (text, 1000000000)
+    };
+
+    let (whole, fraction) = match number.find('.') {
+        Some(dot) => (&number[.. dot], &number[dot + 1 ..]),
+        None => (number, ""),
+    };
+    if whole.is_empty() && fraction.is_empty() {
+        return invalid();
+    }
+
+    let mut nanos: u64 = 0;
+    for c in whole.chars() {
+        let digit = match c.to_digit(10) {
+            Some(digit) => digit as u64,
+            None => return invalid(),
+        };
+        nanos = match nanos.checked_mul(10).and_then(|n| n.checked_add(digit * nanos_per_unit)) {
+            Some(nanos) => nanos,
+            None => return invalid(),
+        };
+    }
+
+    //Digits past a nanosecond are dropped
+    let mut place = nanos_per_unit;
+    for c in fraction.chars() {
+        let digit = match c.to_digit(10) {
+            Some(digit) => digit as u64,
+            None => return invalid(),
+        };
+        place /= 10;
+        nanos = match nanos.checked_add(digit * place) {
+            Some(nanos) => nanos,
+            None => return invalid(),
+        };
+    }
+
+    Ok(((nanos / 1000000000) as i64, (nanos % 1000000000) as i32))
+}
+
+#[cfg(test)]
+mod tests {
+    use redox::time::{Duration, FakeClock};
+
+    use super::*;
+
+    #[test]
+    fn timestamps() {
+        let epoch = DateTime::from_timestamp(0);
+        assert_eq!(epoch.format("%F %T").ok().unwrap(), "1970-01-01 00:00:00");
+
+        let date = DateTime::from_timestamp(951782400);
+        assert_eq!(date.format("%Y-%m-%d").ok().unwrap(), "2000-02-29");
+        assert_eq!(date.timestamp(), 951782400);
+
+        let before = DateTime::from_timestamp(-1);
+        assert_eq!(before.format("%F %T").ok().unwrap(), "1969-12-31 23:59:59");
+    }
+
+    #[test]
+    fn specifiers() {
+        let date = DateTime::from_timestamp(1234567890);
+        assert_eq!(date.format("%H:%M:%S").ok().unwrap(), "23:31:30");
+        assert_eq!(date.format("%j").ok().unwrap(), "044");
+        assert_eq!(date.format("%s").ok().unwrap(), "1234567890");
+        assert_eq!(date.format("100%%").ok().unwrap(), "100%");
+        assert!(date.format("%q").is_err());
+        assert!(date.format("%").is_err());
+    }
+
+    #[test]
+    fn touch_timestamps() {
+        let clock = FakeClock::new();
+        clock.set_realtime(Duration::new(1234567890, 0));
+        let now = DateTime::now(&clock);
+        let full = DateTime::parse_touch("202401021304.05", &now).ok().unwrap();
+        assert_eq!(full.format("%F %T").ok().unwrap(), "2024-01-02 13:04:05");
+
+        let short = DateTime::parse_touch("9901021304", &now).ok().unwrap();
+        assert_eq!(short.year, 1999);
+
+        let no_year = DateTime::parse_touch("01021304", &now).ok().unwrap();
+        assert_eq!(no_year.year, 2009);
+
+        assert!(DateTime::parse_touch("0230", &now).is_err());
+        assert!(DateTime::parse_touch("202302291200", &now).is_err());
+        assert!(DateTime::parse_touch("202401021304.5", &now).is_err());
+        assert!(DateTime::parse_touch("2024x1021304", &now).is_err());
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("2"), Ok((2, 0)));
+        assert_eq!(parse_duration("0.5"), Ok((0, 500000000)));
+        assert_eq!(parse_duration("1.25s"), Ok((1, 250000000)));
+        assert_eq!(parse_duration(".5"), Ok((0, 500000000)));
+        assert_eq!(parse_duration("100ms"), Ok((0, 100000000)));
+        assert_eq!(parse_duration("1500ms"), Ok((1, 500000000)));
+        assert_eq!(parse_duration("0.5ms"), Ok((0, 500000)));
+
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("s").is_err());
+        assert!(parse_duration(".").is_err());
+        assert!(parse_duration("-1").is_err());
+        assert!(parse_duration("1m").is_err());
+        assert!(parse_duration("1.2.3").is_err());
+        assert!(parse_duration("99999999999999999999").is_err());
+    }
+}
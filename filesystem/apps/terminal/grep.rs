@@ -0,0 +1,82 @@
+use redox::string::{String, ToString};
+
+/// How a line is matched
+/// Only substrings for now, a regular expression kind can be added alongside
+pub enum Pattern {
+    /// The line contains the text
+    Substring(String),
+}
+
+/// Decides which lines `grep` prints
+pub struct Matcher {
+    pattern: Pattern,
+    /// Compare ASCII letters without their case
+    ignore_case: bool,
+    /// Print the lines that do not match instead
+    invert: bool,
+}
+
+/// Lower case the ASCII letters of some text
+fn ascii_lowercase(text: &str) -> String {
+    text.chars()
+        .map(|c| if c >= 'A' && c <= 'Z' {
+            (c as u8 - b'A' + b'a') as char
+        } else {
+            c
+        })
+        .collect()
+}
+
+impl Matcher {
+    /// Create a matcher for a substring
+    pub fn substring(pattern: &str, ignore_case: bool, invert: bool) -> Matcher {
+        let pattern = if ignore_case {
+            ascii_lowercase(pattern)
+        } else {
+            pattern.to_string()
+        };
+
+        Matcher {
+            pattern: Pattern::Substring(pattern),
+            ignore_case: ignore_case,
+            invert: invert,
+        }
+    }
+
+    /// Should the line be printed?
+    pub fn is_match(&self, line: &str) -> bool {
+        let found = match self.pattern {
+            Pattern::Substring(ref pattern) => if self.ignore_case {
+                ascii_lowercase(line).contains(&pattern[..])
+            } else {
+                line.contains(&pattern[..])
+            },
+        };
+        found != self.invert
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substring() {
+        let matcher = Matcher::substring("foo", false, false);
+        assert!(matcher.is_match("a foo b"));
+        assert!(!matcher.is_match("a Foo b"));
+        assert!(!matcher.is_match(""));
+        assert!(Matcher::substring("", false, false).is_match("anything"));
+    }
+
+    #[test]
+    fn flags() {
+        let ignore_case = Matcher::substring("FoO", true, false);
+        assert!(ignore_case.is_match("a foo b"));
+        assert!(ignore_case.is_match("FOO"));
+
+        let invert = Matcher::substring("foo", false, true);
+        assert!(!invert.is_match("foo"));
+        assert!(invert.is_match("bar"));
+    }
+}
@@ -1,13 +1,34 @@
 use redox::get_slice::GetSlice;
+use redox::glob::{glob_match, is_glob};
+use redox::iter::Peekable;
+use redox::mem;
 use redox::ops::DerefMut;
+use redox::slice::SliceConcatExt;
 use redox::string::*;
 use redox::vec::Vec;
 use redox::boxed::Box;
+use redox::cell::Cell;
+use redox::cmp;
+use redox::collections::{btree_map, BTreeMap, BTreeSet, VecDeque};
 use redox::fs::*;
 use redox::io::*;
+use redox::syscall::{sys_exit, sys_write, sys_yield};
 use redox::env::*;
-use redox::time::Duration;
+use redox::time::{Duration, SystemClock};
 use redox::to_num::*;
+use redox::usize;
+
+use orbital::console::{CLEAR, COOKED_MODE, POLL_INTERRUPT, RAW_MODE};
+
+use self::date::DateTime;
+use self::grep::Matcher;
+use self::net::{HttpResponse, NetUrl};
+
+pub mod arith;
+pub mod date;
+pub mod grep;
+pub mod net;
+pub mod printf;
 
 /* Magic Macros { */
 static mut application: *mut Application<'static> = 0 as *mut Application;
@@ -20,14 +41,75 @@ macro_rules! exec {
         }
     })
 }
+
+/// Has the running command been interrupted?
+macro_rules! interrupted {
+    () => ({
+        unsafe {
+            (*application).interrupted.get()
+        }
+    })
+}
+
+/// Print an error from a command, and set its status in `?` to 1
+macro_rules! fail {
+    ($($arg:tt)*) => ({
+        println!($($arg)*);
+        unsafe {
+            (*application).set_var("?", "1");
+        }
+    })
+}
+
+/// Get the console size, in character cells
+macro_rules! console_size {
+    () => ({
+        unsafe {
+            (*application).console_size
+        }
+    })
+}
 /* } Magic Macros */
 
+/// How many iterations a long running loop does before yielding to the scheduler
+pub const YIELD_INTERVAL: usize = 64;
+
+/// Yield to the scheduler on every `YIELD_INTERVAL`th iteration, so that long running
+/// commands do not freeze the rest of the system
+/// This is also when Ctrl-C on the console is noticed, interrupting the running command
+pub fn cooperate(iteration: usize) {
+    if iteration % YIELD_INTERVAL == YIELD_INTERVAL - 1 {
+        unsafe { sys_yield() };
+        if poll_interrupt() {
+            unsafe { (*application).interrupted.set(true) };
+        }
+    }
+}
+
+/// Check, without blocking, if Ctrl-C was pressed on the console since the last check
+/// A window console only sees keys when asked, so it is asked first. This is written
+/// straight to the console, as output written while capturing would not reach it
+pub fn poll_interrupt() -> bool {
+    if stdout().path().map_or(false, |path| path.starts_with("console:")) {
+        unsafe { sys_write(1, POLL_INTERRUPT.as_ptr(), POLL_INTERRUPT.len()) };
+    }
+
+    match File::open("debug:interrupt") {
+        Some(mut file) => {
+            let mut buf = [0; 1];
+            file.read(&mut buf) == Some(1)
+        }
+        None => false,
+    }
+}
+
 /// Structure which represents a Terminal's command.
 /// This command structure contains a name, and the code which run the functionnality associated to this one, with zero, one or several argument(s).
 /// # Example
 /// ```
 /// let my_command = Command {
 ///     name: "my_command",
+///     help: "Say hello\n    Usage: my_command",
 ///     main: box|args: &Vec<String>| {
 ///         println!("Say 'hello' to my command! :-D");
 ///     }
@@ -35,403 +117,3502 @@ macro_rules! exec {
 /// ```
 pub struct Command<'a> {
     pub name: &'a str,
+    /// The help text; the first line is a synopsis, the rest describes the usage
+    pub help: &'a str,
     pub main: Box<Fn(&Vec<String>)>,
 }
 
 impl<'a> Command<'a> {
-    /// Return the vector of the commands
-    // TODO: Use a more efficient collection instead
+    /// Return the vector of the builtin commands
+    /// These are looked up through a `CommandRegistry`
     pub fn vec() -> Vec<Self> {
         let mut commands: Vec<Self> = Vec::new();
 
         commands.push(Command {
-            name: "cat",
+            name: ".",
+            help: "Run each line of a script as a command, the same as source\n    Usage: . <script>",
+            main: Box::new(|args: &Vec<String>| source(args)),
+        });
+
+        commands.push(Command {
+            name: "[",
+            help: "Test an expression, the same as test, ending with ]\n    Usage: [ <expression> ]",
             main: Box::new(|args: &Vec<String>| {
-                let path = {
+                match args.last() {
+                    Some(last) if args.len() > 1 && last == "]" => {
+                        test_command(args.get_slice(Some(1), Some(args.len() - 1)));
+                    }
+                    _ => {
+                        println!("Missing ]");
+                        unsafe {
+                            (*application).set_var("?", "2");
+                        }
+                    }
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "alias",
+            help: "Define a command that is replaced by some words, or list the aliases\n    Usage: alias [name] [words]\n    The words of an alias are not searched for aliases again",
+            main: Box::new(|args: &Vec<String>| {
+                unsafe {
                     match args.get(1) {
-                        Some(arg) => arg.clone(),
-                        None => String::new(),
+                        Some(name) => match args.get(2) {
+                            Some(words) => {
+                                (*application).aliases.insert(name.clone(), words.clone());
+                            }
+                            None => match (*application).aliases.get(name) {
+                                Some(words) => println!("alias {} {}", quote(name), quote(words)),
+                                None => fail!("No alias: {}", name),
+                            },
+                        },
+                        None => (*application).print_aliases(),
                     }
-                };
+                }
+            }),
+        });
 
-                if let Some(mut file) = File::open(&path) {
-                    let mut string = String::new();
-                    match file.read_to_string(&mut string) {
-                        Some(_) => println!("{}", string),
-                        None => println!("Failed to read: {}", path),
+        commands.push(Command {
+            name: "assert",
+            help: "Print a diagnostic and set ? to 1 if the comparison does not hold\n    Usage: assert <left> <comparison> <right>\n           assert <-e|-f|-d> <path>\n    Comparisons and file tests are as for if",
+            main: Box::new(|_: &Vec<String>| {}),
+        });
+
+        commands.push(Command {
+            name: "cat",
+            help: "Print the contents of files one after another, or the input\n    Usage: cat [-n] [-b] [file]...\n    -n numbers every line\n    -b numbers the lines that are not blank\n    A file that cannot be read is reported, and the others are still printed",
+            main: Box::new(|args: &Vec<String>| {
+                let mut number = false;
+                let mut number_blank = true;
+                let mut paths = Vec::new();
+                for arg in args.iter().skip(1) {
+                    match &arg[..] {
+                        "-n" => number = true,
+                        "-b" => {
+                            number = true;
+                            number_blank = false;
+                        }
+                        _ => paths.push(arg.clone()),
+                    }
+                }
+
+                let mut strings = Vec::new();
+                if paths.is_empty() {
+                    match take_input() {
+                        Some(input) => strings.push(input),
+                        None => {
+                            fail!("No file given");
+                            return;
+                        }
                     }
                 } else {
-                    println!("Failed to open file: {}", path);
+                    //read_file reports the files that fail, and sets ? to 1
+                    for path in paths.iter() {
+                        if let Some(string) = read_file(path) {
+                            strings.push(string);
+                        }
+                    }
+                }
+
+                //Lines are numbered on from one file to the next
+                let mut line_number = 0;
+                for string in strings.iter() {
+                    if number {
+                        for line in string.lines() {
+                            if number_blank || !line.is_empty() {
+                                line_number += 1;
+                                println!("{:>6}\t{}", line_number, line);
+                            } else {
+                                println!("");
+                            }
+                        }
+                    } else if string.ends_with('\n') {
+                        print!("{}", string);
+                    } else {
+                        println!("{}", string);
+                    }
                 }
             }),
         });
 
         commands.push(Command {
             name: "cd",
+            help: "Change the current directory\n    Usage: cd [directory]\n    Without a directory, goes to HOME, or /home/ if it is not set\n    cd - goes back to the previous directory, kept in OLDPWD",
             main: Box::new(|args: &Vec<String>| {
-                match args.get(1) {
-                    Some(path) => {
-                        if !change_cwd(&path) {
-                            println!("Bad path: {}", path);
+                unsafe {
+                    let path = match args.get(1).map(|arg| &arg[..]) {
+                        Some("-") => match (*application).get_var("OLDPWD") {
+                            Some(old) => old,
+                            None => {
+                                fail!("No previous directory");
+                                return;
+                            }
+                        },
+                        Some(path) => path.to_string(),
+                        None => (*application).get_var("HOME").unwrap_or(HOME.to_string()),
+                    };
+
+                    let old = (*application).get_current_directory();
+                    if change_cwd(&path) {
+                        (*application).set_var("OLDPWD", &old);
+                        if args.get(1).map_or(false, |arg| arg == "-") {
+                            println!("{}", (*application).get_current_directory());
                         }
+                    } else {
+                        fail!("Bad path: {}", path);
                     }
-                    None => println!("No path given")
                 }
             }),
         });
 
         commands.push(Command {
-            name: "echo",
-            main: Box::new(|args: &Vec<String>| {
-                let echo = args.iter()
-                    .skip(1)
-                    .fold(String::new(), |string, arg| string + " " + arg);
-                println!("{}", echo.trim());
+            name: "clear",
+            help: "Erase the console and move the cursor to the top\n    Usage: clear",
+            main: Box::new(|_: &Vec<String>| {
+                print_flush(CLEAR);
             }),
         });
 
         commands.push(Command {
-            name: "else",
-            main: Box::new(|_: &Vec<String>| {}),
+            name: "cols",
+            help: "Print the width of the console in characters\n    Usage: cols",
+            main: Box::new(|_: &Vec<String>| {
+                println!("{}", console_size!().0);
+            }),
         });
 
         commands.push(Command {
-            name: "exec",
+            name: "cp",
+            help: "Copy a file, or a directory with -r\n    Usage: cp [-r] <source> <destination>\n    -r copies a directory and everything in it",
             main: Box::new(|args: &Vec<String>| {
-                if let Some(arg) = args.get(1) {
-                    let mut args_str: Vec<&str> = Vec::new();
-                    for arg in args.get_slice(Some(2), None) {
-                        args_str.push(arg);
+                let mut recursive = false;
+                let mut paths = Vec::new();
+                for arg in args.iter().skip(1) {
+                    if arg == "-r" {
+                        recursive = true;
+                    } else {
+                        paths.push(arg.clone());
+                    }
+                }
+
+                if paths.len() != 2 {
+                    fail!("Usage: cp [-r] <source> <destination>");
+                    return;
+                }
+
+                if is_dir(&paths[0]) {
+                    if !recursive {
+                        fail!("{} is a directory, use -r to copy it", paths[0]);
+                        return;
+                    }
+
+                    let src = dir_path(&paths[0]);
+                    let dst = dir_path(&paths[1]);
+                    let cwd = unsafe { (*application).get_current_directory() };
+                    if dir_path(&canonical_path(&cwd, &dst)).starts_with(&dir_path(&canonical_path(&cwd, &src))) {
+                        fail!("Cannot copy {} into itself", src);
+                        return;
                     }
 
-                    File::exec(arg, &args_str);
+                    let errors = copy_dir(&src, &dst, 0);
+                    if errors > 0 {
+                        fail!("{} files could not be copied", errors);
+                    }
+                } else {
+                    if !copy_file(&paths[0], &file_target(&paths[0], &paths[1])) {
+                        unsafe { (*application).set_var("?", "1") };
+                    }
                 }
             }),
         });
 
         commands.push(Command {
-            name: "exit",
-            main: Box::new(|_: &Vec<String>| {}),
+            name: "date",
+            help: "Print the date and time in UTC\n    Usage: date [+format]\n    The format uses %Y, %m, %d, %H, %M, %S, %j, %s, %F, %T and %%",
+            main: Box::new(|args: &Vec<String>| {
+                let format = match args.get(1) {
+                    Some(arg) => if arg.starts_with('+') {
+                        &arg[1 ..]
+                    } else {
+                        fail!("Format must start with +: {}", arg);
+                        return;
+                    },
+                    None => "%F %T",
+                };
+
+                match DateTime::now(&SystemClock).format(format) {
+                    Ok(output) => println!("{}", output),
+                    Err(error) => fail!("{}", error),
+                }
+            }),
         });
 
         commands.push(Command {
-            name: "fi",
-            main: Box::new(|_: &Vec<String>| {}),
+            name: "declare",
+            help: "Print the variables and aliases as commands that set them again\n    Usage: declare\n    Running the output with source recreates them",
+            main: Box::new(|_: &Vec<String>| {
+                unsafe {
+                    (*application).print_variables();
+                    (*application).print_aliases();
+                }
+            }),
         });
 
         commands.push(Command {
-            name: "if",
+            name: "done",
+            help: "End the innermost while or for\n    Usage: done",
             main: Box::new(|_: &Vec<String>| {}),
         });
 
         commands.push(Command {
-            name: "ls",
+            name: "echo",
+            help: "Print text\n    Usage: echo [-e] [-n] [text]...\n    -e interprets backslash escapes such as \\n and \\xNN\n    \\xNN above \\x7f is the Latin-1 character, written as UTF-8, not a single byte\n    -n omits the trailing newline",
             main: Box::new(|args: &Vec<String>| {
-                let path = {
-                    match args.get(1) {
-                        Some(arg) => arg.clone(),
-                        None => String::new(),
+                let mut escapes = false;
+                let mut newline = true;
+                let mut skip = 1;
+                for arg in args.iter().skip(1) {
+                    match &arg[..] {
+                        "-e" => escapes = true,
+                        "-n" => newline = false,
+                        "-en" | "-ne" => {
+                            escapes = true;
+                            newline = false;
+                        }
+                        _ => break,
                     }
+                    skip += 1;
+                }
+
+                let echo = args.iter()
+                    .skip(skip)
+                    .fold(String::new(), |string, arg| string + " " + arg);
+                let output = if escapes {
+                    unescape(echo.trim())
+                } else {
+                    echo.trim().to_string()
                 };
 
-                if let Some(dir) = read_dir(&path) {
-                    for entry in dir {
-                        println!("{}", entry.path());
-                    }
+                if newline {
+                    println!("{}", output);
                 } else {
-                    println!("Failed to open directory: {}", path);
+                    print_flush(&output);
                 }
             }),
         });
 
         commands.push(Command {
-            name: "mkdir",
-            main: Box::new(|args: &Vec<String>| {
-                match args.get(1) {
-                    Some(dir_name) => if DirEntry::create(dir_name).is_none() {
-                        println!("Failed to create {}", dir_name);
-                    },
-                    None => println!("No name provided")
+            name: "elif",
+            help: "Run the following commands only if no earlier branch of the innermost if ran and the comparison holds\n    Usage: elif <left> <comparison> <right>",
+            main: Box::new(|_: &Vec<String>| {}),
+        });
+
+        commands.push(Command {
+            name: "else",
+            help: "Run the following commands only if no earlier branch of the innermost if ran\n    Usage: else",
+            main: Box::new(|_: &Vec<String>| {}),
+        });
+
+        commands.push(Command {
+            name: "env",
+            help: "Print the exported variables\n    Usage: env",
+            main: Box::new(|_: &Vec<String>| {
+                unsafe {
+                    for name in (*application).exported.iter() {
+                        if let Some(value) = (*application).variables.get(name) {
+                            println!("{}={}", name, value);
+                        }
+                    }
                 }
             }),
         });
 
         commands.push(Command {
-            name: "pwd",
-            main: Box::new(|_: &Vec<String>| {
-                if let Some(file) = File::open("") {
-                    if let Some(path) = file.path() {
-                        println!("{}", path);
-                    } else {
-                        println!("Could not get the path");
+            name: "exec",
+            help: "Run a program\n    Usage: exec <program> [arguments]... [&]\n    The program runs alongside the shell; with a trailing & its PID is kept for jobs and wait\n    A program without a / is searched for in the directories in PATH, separated by :,\n    or in the current directory if PATH is not set",
+            main: Box::new(|args: &Vec<String>| {
+                if let Some(arg) = args.get(1) {
+                    let program = match find_program(arg) {
+                        Some(program) => program,
+                        None => {
+                            println!("exec: command not found: {}", arg);
+                            unsafe {
+                                (*application).set_var("?", "127");
+                            }
+                            return;
+                        }
+                    };
+
+                    let mut args_str: Vec<&str> = Vec::new();
+                    for arg in args.get_slice(Some(2), None) {
+                        args_str.push(arg);
+                    }
+
+                    let background = unsafe { (*application).background };
+                    let pids = if background { context_pids() } else { Vec::new() };
+
+                    if !File::exec(&program, &args_str) {
+                        fail!("Failed to run: {}", program);
+                    } else if background {
+                        //execve does not return the PID, so it is the newest one that was not listed before
+                        if let Some(pid) = context_pids().into_iter().filter(|pid| !pids.contains(pid)).max() {
+                            unsafe { (*application).jobs.push((pid, args.get_slice(Some(1), None).join(" "))) };
+                            println!("{}", pid);
+                        }
                     }
                 } else {
-                    println!("Could not open the working directory");
+                    fail!("No program given");
                 }
             }),
         });
 
         commands.push(Command {
-            name: "read",
+            name: "exit",
+            help: "Exit the shell\n    Usage: exit",
             main: Box::new(|_: &Vec<String>| {}),
         });
 
         commands.push(Command {
-            name: "run",
+            name: "export",
+            help: "Mark variables as exported, setting them if a value is given\n    Usage: export <name>[=value]...\n    The exported variables are listed by env; programs run by exec do not receive them yet,\n    as the kernel has no environment to pass them in",
             main: Box::new(|args: &Vec<String>| {
-                if let Some(path) = args.get(1) {
-
-                    let mut commands = String::new();
-                    if let Some(mut file) = File::open(path) {
-                        file.read_to_string(&mut commands);
+                if args.len() < 2 {
+                    fail!("No variable name given");
+                    return;
+                }
+                for arg in args.iter().skip(1) {
+                    let (name, value) = match arg.find('=') {
+                        Some(i) => (&arg[.. i], Some(&arg[i + 1 ..])),
+                        None => (&arg[..], None),
+                    };
+                    if name.is_empty() || !name.chars().all(is_name_char) {
+                        fail!("Invalid variable name: {}", name);
+                        continue;
                     }
-
-                    for command in commands.split('\n') {
-                        exec!(command);
+                    unsafe {
+                        if let Some(value) = value {
+                            (*application).set_var(name, value);
+                        }
+                        (*application).exported.insert(name.to_string());
                     }
                 }
             }),
         });
 
         commands.push(Command {
-            name: "sleep",
+            name: "fi",
+            help: "End the innermost if\n    Usage: fi",
+            main: Box::new(|_: &Vec<String>| {}),
+        });
+
+        commands.push(Command {
+            name: "find",
+            help: "Print the paths under a directory whose names match, and how many there were\n    Usage: find [directory] <name>\n    The name can have wildcards, quoted so they are not expanded by the shell\n    Searches the current directory if none is given",
             main: Box::new(|args: &Vec<String>| {
-                let secs = {
-                    match args.get(1) {
-                        Some(arg) => arg.to_num() as i64,
-                        None => 0,
+                let (dir, pattern) = match (args.get(1), args.get(2)) {
+                    (Some(dir), Some(pattern)) => (dir_path(dir), pattern),
+                    (Some(pattern), None) => (String::new(), pattern),
+                    _ => {
+                        fail!("No name given");
+                        return;
                     }
                 };
 
-                let nanos = {
-                    match args.get(2) {
-                        Some(arg) => arg.to_num() as i32,
-                        None => 0,
-                    }
-                };
+                if !dir.is_empty() && read_dir(&dir).is_none() {
+                    fail!("Failed to open directory: {}", dir);
+                    return;
+                }
 
-                println!("Sleep: {} {}", secs, nanos);
-                let remaining = Duration::new(secs, nanos).sleep();
-                println!("Remaining: {} {}", remaining.secs, remaining.nanos);
+                let found = find_paths(&dir, pattern, 0);
+                println!("{} found", found);
             }),
         });
 
         commands.push(Command {
-            name: "send",
+            name: "for",
+            help: "Run the commands up to the matching done once for every word, set in the variable\n    Usage: for <variable> in <words>...\n    Variables in the words are split on spaces, and wildcards are expanded",
+            main: Box::new(|_: &Vec<String>| {}),
+        });
+
+        commands.push(Command {
+            name: "free",
+            help: "Print the memory used and free, from memory:\n    Usage: free [-h]\n    -h prints the total, used and free memory in KiB, MiB or GiB",
             main: Box::new(|args: &Vec<String>| {
-                if args.len() < 3 {
-                    println!("Error: incorrect arguments");
-                    println!("Usage: send [url] [data]");
+                let human = args.get(1).map_or(false, |arg| arg == "-h");
+
+                let string = match read_file("memory:") {
+                    Some(string) => string,
+                    None => return,
+                };
+                if !human {
+                    println!("{}", string);
                     return;
                 }
 
-                let path = {
-                    match args.get(1) {
-                        Some(arg) => arg.clone(),
-                        None => String::new(),
+                //Lines are like Memory Used: 1024 KB
+                let mut used = None;
+                let mut free = None;
+                for line in string.lines() {
+                    let mut parts = line.splitn(2, ':');
+                    let label = parts.next().unwrap_or("").trim();
+                    let mut value = parts.next().unwrap_or("").split_whitespace();
+                    let number = value.next().unwrap_or("");
+                    let unit = match value.next() {
+                        Some("B") => 1,
+                        Some("KB") => 1024,
+                        Some("MB") => 1024 * 1024,
+                        Some("GB") => 1024 * 1024 * 1024,
+                        _ => continue,
+                    };
+                    if number.is_empty() || !number.chars().all(|c| c.is_digit(10)) {
+                        continue;
                     }
-                };
-
-                if let Some(mut file) = File::open(&path) {
-                    println!("URL: {:?}", file.path());
-
-                    let string: String = args.iter()
-                        .skip(2)
-                        .fold(String::new(), |s, arg| s + " " + arg)
-                        + "\r\n\r\n";
-
-                    match file.write(string.trim_left().as_bytes()) {
-                        Some(size) => println!("Wrote {} bytes", size),
-                        None => println!("Failed to write"),
+                    let bytes = number.to_num() as u64 * unit;
+                    match label {
+                        "Memory Used" => used = Some(bytes),
+                        "Memory Free" => free = Some(bytes),
+                        _ => (),
                     }
+                }
 
-                    let mut string = String::new();
-                    match file.read_to_string(&mut string) {
-                        Some(_) => println!("{}", string),
-                        None => println!("Failed to read"),
+                match (used, free) {
+                    (Some(used), Some(free)) => {
+                        println!("{:<6}{:>10}{:>10}{:>10}", "", "total", "used", "free");
+                        println!("{:<6}{:>10}{:>10}{:>10}",
+                                 "Mem:",
+                                 human_size(used + free),
+                                 human_size(used),
+                                 human_size(free));
+                    }
+                    _ => {
+                        println!("Could not parse the memory usage, printing it as it is");
+                        println!("{}", string);
                     }
                 }
             }),
         });
 
-        // Simple command to create a file, in the current directory
-        // The file has got the name given as the first argument of the command
-        // If the command have no arguments, the command don't create the file
         commands.push(Command {
-            name: "touch",
+            name: "grep",
+            help: "Print the lines of files, or of the input, that contain a pattern\n    Usage: grep [-i] [-v] <pattern> [file]...\n    -i ignores the case of letters\n    -v prints the lines that do not contain the pattern\n    Sets ? to 1 if no line was printed",
             main: Box::new(|args: &Vec<String>| {
-                match args.get(1) {
-                    Some(file_name) => if File::create(file_name).is_none() {
-                        println!("Failed to create: {}", file_name);
-                    },
-                    None => println!("No name provided")
+                let mut ignore_case = false;
+                let mut invert = false;
+                let mut pattern = None;
+                let mut paths = Vec::new();
+                for arg in args.iter().skip(1) {
+                    match &arg[..] {
+                        "-i" if pattern.is_none() => ignore_case = true,
+                        "-v" if pattern.is_none() => invert = true,
+                        _ => if pattern.is_none() {
+                            pattern = Some(arg.clone());
+                        } else {
+                            paths.push(arg.clone());
+                        },
+                    }
+                }
+
+                let matcher = match pattern {
+                    Some(pattern) => Matcher::substring(&pattern, ignore_case, invert),
+                    None => {
+                        fail!("No pattern given");
+                        return;
+                    }
+                };
+
+                let mut sources = Vec::new();
+                if paths.is_empty() {
+                    match take_input() {
+                        Some(input) => sources.push((String::new(), input)),
+                        None => {
+                            fail!("No file given");
+                            return;
+                        }
+                    }
+                } else {
+                    for path in paths.iter() {
+                        match read_file(path) {
+                            Some(string) => sources.push((path.clone(), string)),
+                            None => return,
+                        }
+                    }
+                }
+
+                let mut found = false;
+                for &(ref path, ref string) in sources.iter() {
+                    for (i, line) in string.lines().enumerate() {
+                        if interrupted!() {
+                            return;
+                        }
+                        cooperate(i);
+
+                        if matcher.is_match(line) {
+                            found = true;
+                            if paths.len() > 1 {
+                                println!("{}:{}", path, line);
+                            } else {
+                                println!("{}", line);
+                            }
+                        }
+                    }
+                }
+
+                if !found {
+                    unsafe {
+                        (*application).set_var("?", "1");
+                    }
                 }
             }),
         });
 
         commands.push(Command {
-            name: "url_hex",
+            name: "head",
+            help: "Print the first lines of a file, or of the input\n    Usage: head [-n count] [file]\n    Prints 10 lines if no count is given",
             main: Box::new(|args: &Vec<String>| {
-                let path = {
-                    match args.get(1) {
-                        Some(arg) => arg.clone(),
-                        None => String::new(),
+                let (count, path) = match line_count_args(args) {
+                    Ok(count_path) => count_path,
+                    Err(error) => {
+                        fail!("{}", error);
+                        return;
                     }
                 };
 
-                if let Some(mut file) = File::open(&path) {
-                    let mut vec: Vec<u8> = Vec::new();
-                    match file.read_to_end(&mut vec) {
-                        Some(_) => {
-                            let mut line = "HEX:".to_string();
-                            for byte in vec.iter() {
-                                line = line + " " + &format!("{:X}", *byte);
-                            }
-                            println!("{}", line);
-                        }
-                        None => println!("Failed to read"),
+                if let Some(string) = file_or_input(path) {
+                    for line in string.lines().take(count) {
+                        println!("{}", line);
                     }
                 }
             }),
         });
 
         commands.push(Command {
-            name: "wget",
+            name: "hexdump",
+            help: "Print the bytes of a file, 16 per line, with offsets and ASCII\n    Usage: hexdump [-s offset] [-n length] <file>",
             main: Box::new(|args: &Vec<String>| {
-                if let Some(host) = args.get(1) {
-                    if let Some(req) = args.get(2) {
-                        if let Some(mut con) = File::open(&("tcp://".to_string() + host)) {
-                            con.write(("GET ".to_string() + req + " HTTP/1.1").as_bytes());
+                let mut offset = 0;
+                let mut length = None;
+                let mut path = String::new();
+
+                let mut i = 1;
+                while i < args.len() {
+                    let arg = &args[i];
+                    if arg == "-s" || arg == "-n" {
+                        match args.get(i + 1) {
+                            Some(value) => if arg == "-s" {
+                                offset = value.to_num();
+                            } else {
+                                length = Some(value.to_num());
+                            },
+                            None => {
+                                fail!("No value given for {}", arg);
+                                return;
+                            }
+                        }
+                        i += 2;
+                    } else {
+                        path = arg.clone();
+                        i += 1;
+                    }
+                }
 
-                            let mut res = Vec::new();
-                            con.read_to_end(&mut res);
+                if let Some(mut file) = File::open(&path) {
+                    if offset > 0 && file.seek(SeekFrom::Start(offset)) != Some(offset) {
+                        fail!("Failed to seek: {}", path);
+                        return;
+                    }
 
-                            if let Some(mut file) = File::open(&req) {
-                                file.write(&res);
+                    let mut line: Vec<u8> = Vec::new();
+                    let mut line_offset = offset;
+                    let mut remaining = length.unwrap_or(usize::MAX);
+                    let mut buf = [0; 4096];
+                    let mut iteration = 0;
+                    while remaining > 0 {
+                        cooperate(iteration);
+                        iteration += 1;
+
+                        let count = match file.read(&mut buf) {
+                            Some(0) => break,
+                            Some(count) => cmp::min(count, remaining),
+                            None => {
+                                fail!("Failed to read: {}", path);
+                                break;
+                            }
+                        };
+                        remaining -= count;
+
+                        for b in buf[.. count].iter() {
+                            line.push(*b);
+                            if line.len() == 16 {
+                                println!("{}", hex_line(line_offset, &line));
+                                line_offset += line.len();
+                                line.clear();
                             }
                         }
-                    } else {
-                        println!("No request given");
                     }
+
+                    if !line.is_empty() {
+                        println!("{}", hex_line(line_offset, &line));
+                        line_offset += line.len();
+                    }
+                    println!("{:08x}", line_offset);
                 } else {
-                    println!("No url given");
+                    fail!("Failed to open file: {}", path);
                 }
             }),
         });
 
-        let command_list = commands.iter().fold(String::new(), |l , c| l + " " + c.name);
-
         commands.push(Command {
-            name: "help",
-            main: Box::new(move |_: &Vec<String>| {
-                println!("Commands:{}", command_list);
+            name: "history",
+            help: "List the commands entered at the prompt, which can be run again with !n or !!\n    Usage: history\n    The commands are kept in /home/.history between sessions",
+            main: Box::new(|_: &Vec<String>| {
+                unsafe {
+                    for (i, command) in (*application).history.iter().enumerate() {
+                        println!("{:>4}  {}", i, command);
+                    }
+                }
             }),
-         });
+        });
+
+        commands.push(Command {
+            name: "if",
+            help: "Run the following commands only if the comparison holds\n    Usage: if <left> <comparison> <right>\n           if <-e|-f|-d> <path>\n    Comparisons are ==, !=, >, >=, < and <=\n    -e tests that the path exists, -f that it is a file and -d that it is a directory",
+            main: Box::new(|_: &Vec<String>| {}),
+        });
+
+        commands.push(Command {
+            name: "jobs",
+            help: "List the programs run in the background with exec and a trailing & that are still running\n    Usage: jobs",
+            main: Box::new(|_: &Vec<String>| {
+                let pids = context_pids();
+                unsafe {
+                    (*application).jobs.retain(|&(pid, _)| pids.contains(&pid));
+                    for &(pid, ref command) in (*application).jobs.iter() {
+                        println!("{:>5} {}", pid, command);
+                    }
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "kill",
+            help: "Stop programs, by the PID that ps lists\n    Usage: kill [-9] <pid>...\n    Processes can not handle signals, so -9 is accepted but does the same",
+            main: Box::new(|args: &Vec<String>| {
+                let mut pids = Vec::new();
+                for arg in args.get_slice(Some(1), None).iter() {
+                    if arg == "-9" {
+                        continue;
+                    }
+                    if arg.is_empty() || !arg.chars().all(|c| c.is_digit(10)) {
+                        fail!("Invalid PID: {}", arg);
+                        return;
+                    }
+                    pids.push(arg.to_num());
+                }
+                if pids.is_empty() {
+                    fail!("No PID given");
+                    return;
+                }
+
+                for pid in pids.iter() {
+                    match File::open(&format!("context:{}", pid)) {
+                        Some(mut file) => if file.write(b"kill").is_none() {
+                            fail!("Failed to kill: {}", pid);
+                        },
+                        None => fail!("No process: {}", pid),
+                    }
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "ls",
+            help: "List the contents of a directory\n    Usage: ls [-l] [directory]\n    -l lists one entry a line, with its type and size in bytes, or ? if it is unknown",
+            main: Box::new(|args: &Vec<String>| {
+                let mut long = false;
+                let mut path = String::new();
+                for arg in args.iter().skip(1) {
+                    if arg == "-l" {
+                        long = true;
+                    } else {
+                        path = arg.clone();
+                    }
+                }
+
+                if let Some(dir) = read_dir(&path) {
+                    let entries: Vec<String> = dir.map(|entry| entry.path().to_string()).collect();
+                    if long {
+                        let dir = if path.is_empty() {
+                            path.clone()
+                        } else {
+                            dir_path(&path)
+                        };
+                        println!("{:<4} {:>10} {}", "Type", "Size", "Name");
+                        for (i, entry) in entries.iter().enumerate() {
+                            cooperate(i);
+                            if entry.ends_with('/') {
+                                println!("{:<4} {:>10} {}", "dir", "-", entry);
+                            } else {
+                                let size = file_size(&(dir.clone() + entry))
+                                               .map_or("?".to_string(), |size| format!("{}", size));
+                                println!("{:<4} {:>10} {}", "file", size, entry);
+                            }
+                        }
+                    } else {
+                        print_columns(&entries);
+                    }
+                } else {
+                    fail!("Failed to open directory: {}", path);
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "mapfile",
+            help: "Read lines into the variables array_0, array_1, ... and their number into array_count\n    Usage: mapfile <array> [file]\n    Without a file, reads the redirected input, or the console up to an empty line",
+            main: Box::new(|_: &Vec<String>| {}),
+        });
+
+        commands.push(Command {
+            name: "mkdir",
+            help: "Create a directory\n    Usage: mkdir [-p] <directory>\n    -p also creates the missing directories above it, and is fine if it exists",
+            main: Box::new(|args: &Vec<String>| {
+                let parents = args.get(1).map_or(false, |arg| arg == "-p");
+                match args.get(if parents { 2 } else { 1 }) {
+                    Some(dir_name) => if parents {
+                        if let Err(dir) = create_dir_all(dir_name) {
+                            fail!("Failed to create {}", dir);
+                        }
+                    } else if DirEntry::create(dir_name).is_none() {
+                        fail!("Failed to create {}", dir_name);
+                    },
+                    None => fail!("No name provided")
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "mv",
+            help: "Move a file\n    Usage: mv <source> <destination>\n    A file moved to a directory keeps its name",
+            main: Box::new(|args: &Vec<String>| {
+                let (src, dst) = match (args.get(1), args.get(2)) {
+                    (Some(src), Some(dst)) if args.len() == 3 => (src, dst),
+                    _ => {
+                        fail!("Usage: mv <source> <destination>");
+                        return;
+                    }
+                };
+
+                if is_dir(src) {
+                    fail!("{} is a directory, only files can be moved", src);
+                    return;
+                }
+
+                let target = file_target(src, dst);
+                let cwd = unsafe { (*application).get_current_directory() };
+                if canonical_path(&cwd, &target) == canonical_path(&cwd, src) {
+                    fail!("{} and {} are the same file", src, target);
+                    return;
+                }
+
+                if !copy_file(src, &target) {
+                    unsafe { (*application).set_var("?", "1") };
+                } else if !remove_file(src) {
+                    fail!("Copied to {}, but failed to remove: {}", target, src);
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "printenv",
+            help: "Print the value of a variable, or every variable\n    Usage: printenv [name]\n    An unset variable prints nothing and sets ? to 1",
+            main: Box::new(|args: &Vec<String>| {
+                unsafe {
+                    match args.get(1) {
+                        Some(name) => match (*application).get_var(name) {
+                            Some(value) => println!("{}", value),
+                            None => (*application).set_var("?", "1"),
+                        },
+                        None => for (name, value) in (*application).variables.iter() {
+                            println!("{}={}", name, value);
+                        },
+                    }
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "printf",
+            help: "Print arguments in a format, without a newline unless the format has one\n    Usage: printf <format> [arguments]...\n    %s prints text, %d a decimal and %x a hexadecimal number, %c a character, %% a %\n    A - pads on the right and a 0 with zeros, then come a width and a .precision, as in %-8.3d\n    The format is repeated for any arguments left, and backslash escapes like \\n are interpreted",
+            main: Box::new(|args: &Vec<String>| {
+                let format = match args.get(1) {
+                    Some(format) => unescape(format),
+                    None => {
+                        fail!("No format given");
+                        return;
+                    }
+                };
+
+                match printf::format(&format, args.get_slice(Some(2), None)) {
+                    Ok(output) => {
+                        print_flush(&output);
+                    }
+                    Err(error) => fail!("{}", error),
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "ps",
+            help: "List the processes, from context:\n    Usage: ps [--json]\n    --json prints them as a JSON array, for scripts",
+            main: Box::new(|args: &Vec<String>| {
+                let json = args.get(1).map_or(false, |arg| arg == "--json");
+
+                let string = match read_file("context:") {
+                    Some(string) => string,
+                    None => return,
+                };
+
+                /// A column of a line, from where it starts to where the next one starts
+                fn column<'a>(line: &'a str, starts: &[usize], i: usize) -> &'a str {
+                    line.get_slice(Some(starts[i]), starts.get(i + 1).map(|end| *end)).trim()
+                }
+
+                //Columns start where their names do in the header, the name is last as it can have spaces
+                let mut lines = string.lines();
+                let header = lines.next().unwrap_or("");
+                let mut starts = Vec::new();
+                for name in ["PID", "MEM", "FDS", "STATE", "NAME"].iter() {
+                    let start = match header.find(*name) {
+                        Some(start) => start,
+                        None => break,
+                    };
+                    if starts.last().map_or(false, |last| start <= *last) {
+                        break;
+                    }
+                    starts.push(start);
+                }
+
+                let mut processes = Vec::new();
+                let mut parsed = starts.len() == 5;
+                if parsed {
+                    for line in lines {
+                        let pid = column(line, &starts, 0);
+                        let fds = column(line, &starts, 2);
+                        if pid.is_empty() || fds.is_empty() ||
+                           !pid.chars().all(|c| c.is_digit(10)) || !fds.chars().all(|c| c.is_digit(10)) {
+                            parsed = false;
+                            break;
+                        }
+                        processes.push((pid,
+                                        column(line, &starts, 1),
+                                        fds,
+                                        column(line, &starts, 3),
+                                        line.get_slice(Some(starts[4]), None).trim()));
+                    }
+                }
+
+                if !parsed {
+                    println!("Could not parse the processes, listing them as they are");
+                    println!("{}", string);
+                } else if json {
+                    println!("[");
+                    for (i, process) in processes.iter().enumerate() {
+                        println!("  {{\"pid\": {}, \"name\": {}, \"state\": {}, \"memory\": {}, \"fds\": {}}}{}",
+                                 process.0,
+                                 json_string(process.4),
+                                 json_string(process.3),
+                                 json_string(process.1),
+                                 process.2,
+                                 if i + 1 < processes.len() { "," } else { "" });
+                    }
+                    println!("]");
+                } else {
+                    println!("{:>5} {:<8} {:>8} {:>4} {}", "PID", "STATE", "MEM", "FDS", "NAME");
+                    for process in processes.iter() {
+                        println!("{:>5} {:<8} {:>8} {:>4} {}",
+                                 process.0,
+                                 process.3,
+                                 process.1,
+                                 process.2,
+                                 process.4);
+                    }
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "pwd",
+            help: "Print the current directory\n    Usage: pwd",
+            main: Box::new(|_: &Vec<String>| {
+                if let Some(file) = File::open("") {
+                    if let Some(path) = file.path() {
+                        println!("{}", path);
+                    } else {
+                        fail!("Could not get the path");
+                    }
+                } else {
+                    fail!("Could not open the working directory");
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "read",
+            help: "Read a value for each variable from the console\n    Usage: read <variable>...\n           read -a <array>\n           read -k <variable>\n    -a splits one line into words, stored in array_0, array_1, ... and array_count\n    -k reads a single key, without waiting for enter",
+            main: Box::new(|_: &Vec<String>| {}),
+        });
+
+        commands.push(Command {
+            name: "reset",
+            help: "Clear any unfinished conditions and loops, and the variables if -v is given\n    Usage: reset [-v]\n    Exported variables are kept. A reset is not read into the body of a loop, it ends it",
+            main: Box::new(|_: &Vec<String>| {}),
+        });
+
+        commands.push(Command {
+            name: "rm",
+            help: "Remove files, or directories with -r\n    Usage: rm [-r] [-f] [-v] <path>...\n    -r removes a directory and everything in it\n    -f does not complain about paths that do not exist\n    -v prints every path that is removed\n    Flags can be combined, as in -rf",
+            main: Box::new(|args: &Vec<String>| {
+                let mut recursive = false;
+                let mut force = false;
+                let mut verbose = false;
+                let mut paths = Vec::new();
+                for arg in args.iter().skip(1) {
+                    if arg.starts_with('-') && arg.len() > 1 {
+                        for flag in arg.chars().skip(1) {
+                            match flag {
+                                'r' => recursive = true,
+                                'f' => force = true,
+                                'v' => verbose = true,
+                                _ => {
+                                    fail!("Unknown flag: -{}", flag);
+                                    return;
+                                }
+                            }
+                        }
+                    } else {
+                        paths.push(arg.clone());
+                    }
+                }
+
+                if paths.is_empty() {
+                    fail!("No path given");
+                    return;
+                }
+
+                for path in paths.iter() {
+                    if is_protected(path) {
+                        fail!("Refusing to remove {}", path);
+                    } else if !file_test("-e", path).unwrap_or(false) {
+                        if !force {
+                            fail!("No such file or directory: {}", path);
+                        }
+                    } else if is_dir(path) {
+                        if !recursive {
+                            fail!("{} is a directory, use -r to remove it", path);
+                        } else {
+                            let errors = remove_tree(&dir_path(path), verbose, 0);
+                            if errors > 0 {
+                                fail!("{} entries could not be removed", errors);
+                            }
+                        }
+                    } else if remove_file(path) {
+                        if verbose {
+                            println!("Removed {}", path);
+                        }
+                    } else {
+                        fail!("Failed to remove: {}", path);
+                    }
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "rows",
+            help: "Print the height of the console in characters\n    Usage: rows",
+            main: Box::new(|_: &Vec<String>| {
+                println!("{}", console_size!().1);
+            }),
+        });
+
+        commands.push(Command {
+            name: "run",
+            help: "Run each line of a script as a command, or each .sh script in a directory\n    Usage: run [-e] <script or directory> [arguments]...\n    The arguments are set in the variables 1, 2, ... while the script runs\n    -e stops a script at its first failed assert\n    Sets ? to 0 if no assert failed, or 1 if one did",
+            main: Box::new(|args: &Vec<String>| {
+                let stop = args.get(1).map_or(false, |arg| arg == "-e");
+                let first = if stop { 2 } else { 1 };
+                let path = match args.get(first) {
+                    Some(path) => path,
+                    None => {
+                        fail!("No script given");
+                        return;
+                    }
+                };
+
+                let failed = if is_dir(path) {
+                    run_dir(&dir_path(path), stop)
+                } else {
+                    !run_script(path, args.get_slice(Some(first + 1), None), stop)
+                };
+
+                unsafe {
+                    (*application).set_var("?", if failed { "1" } else { "0" });
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "sleep",
+            help: "Wait for the given time\n    Usage: sleep <duration>\n    The duration is in seconds, which can be fractional as in 0.5, or has a unit, s or ms, as in 100ms",
+            main: Box::new(|args: &Vec<String>| {
+                let (secs, nanos) = match args.get(1) {
+                    Some(arg) => match date::parse_duration(arg) {
+                        Ok(duration) => duration,
+                        Err(error) => {
+                            fail!("{}", error);
+                            return;
+                        }
+                    },
+                    None => {
+                        fail!("No duration given");
+                        return;
+                    }
+                };
+
+                let remaining = Duration::new(secs, nanos).sleep();
+                if remaining.secs > 0 || remaining.nanos > 0 {
+                    fail!("Woke up early, {}.{:09} seconds were left", remaining.secs, remaining.nanos);
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "send",
+            help: "Write data to a URL and print the response\n    Usage: send [-t seconds] <url> <data>...\n    The URL is tcp://host:port, udp://host:port or http://host[:port]\n    -t gives up between reads once the timeout has passed, by default $TIMEOUT or 30 seconds\n    A read that never returns still blocks, so a connection that stops sending can still hang",
+            main: Box::new(|args: &Vec<String>| {
+                let (timeout, args) = timeout_arg(args);
+
+                if args.len() < 3 {
+                    println!("Error: incorrect arguments");
+                    fail!("Usage: send [url] [data]");
+                    return;
+                }
+
+                let url = match NetUrl::parse(&args[1]) {
+                    Ok(url) => url,
+                    Err(err) => {
+                        fail!("{}", err);
+                        return;
+                    }
+                };
+
+                if let Some(mut file) = File::open(&url.socket()) {
+                    println!("URL: {:?}", file.path());
+
+                    let mut string = String::new();
+                    for arg in args.iter().skip(2) {
+                        if !string.is_empty() {
+                            string.push(' ');
+                        }
+                        string.push_str(arg);
+                    }
+                    string.push_str("\r\n\r\n");
+
+                    match file.write_all(string.as_bytes()) {
+                        Some(size) => println!("Wrote {} bytes", size),
+                        None => fail!("Failed to write"),
+                    }
+
+                    let mut response = Vec::new();
+                    match net::read_timeout(&mut file, &mut response, timeout, &SystemClock) {
+                        Some(true) => println!("{}", unsafe { String::from_utf8_unchecked(response) }),
+                        Some(false) => fail!("Timed out after {} seconds", timeout.secs),
+                        None => fail!("Failed to read"),
+                    }
+                } else {
+                    fail!("Failed to connect: {}", url.socket());
+                }
+            }),
+        });
+
+        // Simple command to create a file, in the current directory
+        // The file has got the name given as the first argument of the command
+        // If the command have no arguments, the command don't create the file
+        commands.push(Command {
+            name: "seq",
+            help: "Print a sequence of numbers, one per line\n    Usage: seq [start [step]] <end>\n    Start and step are 1 if not given, a negative step counts down",
+            main: Box::new(|args: &Vec<String>| {
+                let mut numbers = Vec::new();
+                for arg in args.get_slice(Some(1), None).iter() {
+                    match arith::number(arg) {
+                        Ok(number) => numbers.push(number),
+                        Err(error) => {
+                            fail!("{}", error);
+                            return;
+                        }
+                    }
+                }
+
+                let (start, step, end) = match numbers.len() {
+                    1 => (1, 1, numbers[0]),
+                    2 => (numbers[0], 1, numbers[1]),
+                    3 => (numbers[0], numbers[1], numbers[2]),
+                    _ => {
+                        fail!("Usage: seq [start [step]] <end>");
+                        return;
+                    }
+                };
+                if step == 0 {
+                    fail!("The step can not be zero");
+                    return;
+                }
+
+                let mut value = start;
+                let mut iteration = 0;
+                while (step > 0 && value <= end) || (step < 0 && value >= end) {
+                    if interrupted!() {
+                        return;
+                    }
+                    cooperate(iteration);
+                    iteration += 1;
+
+                    println!("{}", value);
+                    value = match value.checked_add(step) {
+                        Some(value) => value,
+                        None => break,
+                    };
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "sort",
+            help: "Print the lines of a file, or of the input, in order\n    Usage: sort [-r] [-n] [file]\n    -r reverses the order, -n compares lines as numbers, where lines that are not count as 0",
+            main: Box::new(|args: &Vec<String>| {
+                let mut reverse = false;
+                let mut numeric = false;
+                let mut path = None;
+                for arg in args.get_slice(Some(1), None).iter() {
+                    match &arg[..] {
+                        "-r" => reverse = true,
+                        "-n" => numeric = true,
+                        _ => path = Some(arg.clone()),
+                    }
+                }
+
+                if let Some(string) = file_or_input(path) {
+                    let mut lines: Vec<&str> = string.lines().collect();
+                    if numeric {
+                        lines.sort_by(|a, b| {
+                            let a = arith::number(a.trim()).unwrap_or(0);
+                            let b = arith::number(b.trim()).unwrap_or(0);
+                            a.cmp(&b)
+                        });
+                    } else {
+                        lines.sort();
+                    }
+                    if reverse {
+                        lines.reverse();
+                    }
+
+                    for line in lines.iter() {
+                        println!("{}", line);
+                    }
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "source",
+            help: "Run each line of a script as a command, in this shell\n    Usage: source <script> [arguments]...\n    Variables, aliases and conditions set by the script are kept afterwards\n    Sets ? to 1 if the script could not be read or an assert in it failed",
+            main: Box::new(|args: &Vec<String>| source(args)),
+        });
+
+        commands.push(Command {
+            name: "tail",
+            help: "Print the last lines of a file, or of the input\n    Usage: tail [-n count] [file]\n    Prints 10 lines if no count is given",
+            main: Box::new(|args: &Vec<String>| {
+                let (count, path) = match line_count_args(args) {
+                    Ok(count_path) => count_path,
+                    Err(error) => {
+                        fail!("{}", error);
+                        return;
+                    }
+                };
+
+                if let Some(string) = file_or_input(path) {
+                    //Only keep the last lines, however long the file is
+                    let mut lines = VecDeque::with_capacity(count);
+                    for (i, line) in string.lines().enumerate() {
+                        cooperate(i);
+                        if count == 0 {
+                            break;
+                        }
+                        if lines.len() == count {
+                            lines.pop_front();
+                        }
+                        lines.push_back(line);
+                    }
+
+                    for line in lines.iter() {
+                        println!("{}", line);
+                    }
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "test",
+            help: "Set ? to 0 if an expression is true, 1 if it is false, or 2 if it is invalid\n    Usage: test <string>\n           test <-z|-n> <string>\n           test <string> <=|!=> <string>\n           test <integer> <-eq|-ne|-lt|-gt|-le|-ge> <integer>\n           test <-e|-f|-d> <path>\n    -z is true for an empty string, -n for one that is not, and a lone string if it is not empty",
+            main: Box::new(|args: &Vec<String>| test_command(args.get_slice(Some(1), None))),
+        });
+
+        commands.push(Command {
+            name: "touch",
+            help: "Create a file\n    Usage: touch [-t [[CC]YY]MMDDhhmm[.ss]] <file>",
+            main: Box::new(|args: &Vec<String>| {
+                let mut i = 1;
+                if args.get(1).map_or(false, |arg| arg == "-t") {
+                    let stamp = match args.get(2) {
+                        Some(stamp) => stamp,
+                        None => {
+                            fail!("No timestamp given");
+                            return;
+                        }
+                    };
+                    let now = DateTime::now(&SystemClock);
+                    if let Err(error) = DateTime::parse_touch(stamp, &now) {
+                        fail!("{}", error);
+                        return;
+                    }
+                    i = 3;
+                }
+
+                match args.get(i) {
+                    Some(file_name) => if File::create(file_name).is_none() {
+                        fail!("Failed to create: {}", file_name);
+                    } else if i > 1 {
+                        println!("File times are not stored, not setting the time of {}", file_name);
+                    },
+                    None => fail!("No name provided")
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "unalias",
+            help: "Remove aliases\n    Usage: unalias <name>...",
+            main: Box::new(|args: &Vec<String>| {
+                if args.len() < 2 {
+                    fail!("No alias name given");
+                    return;
+                }
+                for name in args.iter().skip(1) {
+                    unsafe {
+                        if (*application).aliases.remove(name).is_none() {
+                            fail!("No alias: {}", name);
+                        }
+                    }
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "uniq",
+            help: "Print the lines of a file, or of the input, without adjacent duplicates\n    Usage: uniq [-c] [file]\n    -c prefixes each line with how many times it was repeated\n    Only repeats next to each other are removed, so sort the lines first to remove all of them",
+            main: Box::new(|args: &Vec<String>| {
+                let mut count = false;
+                let mut path = None;
+                for arg in args.get_slice(Some(1), None).iter() {
+                    if arg == "-c" {
+                        count = true;
+                    } else {
+                        path = Some(arg.clone());
+                    }
+                }
+
+                let print = |line: &str, repeats: usize| if count {
+                    println!("{:7} {}", repeats, line);
+                } else {
+                    println!("{}", line);
+                };
+
+                if let Some(string) = file_or_input(path) {
+                    let mut previous: Option<&str> = None;
+                    let mut repeats = 0;
+                    for line in string.lines() {
+                        if previous == Some(line) {
+                            repeats += 1;
+                            continue;
+                        }
+                        if let Some(previous) = previous {
+                            print(previous, repeats);
+                        }
+                        previous = Some(line);
+                        repeats = 1;
+                    }
+                    if let Some(previous) = previous {
+                        print(previous, repeats);
+                    }
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "url_hex",
+            help: "Print the bytes of a URL, 16 per line, with offsets and ASCII\n    Usage: url_hex [-C] <url>\n    The layout is always the canonical one, -C is accepted to match hexdump -C",
+            main: Box::new(|args: &Vec<String>| {
+                let mut path = String::new();
+                for arg in args.get_slice(Some(1), None).iter() {
+                    if arg != "-C" {
+                        path = arg.clone();
+                    }
+                }
+
+                if let Some(mut file) = File::open(&path) {
+                    let mut vec: Vec<u8> = Vec::new();
+                    match file.read_to_end(&mut vec) {
+                        Some(_) => {
+                            for (i, line) in vec.chunks(16).enumerate() {
+                                cooperate(i);
+                                println!("{}", hex_line(i * 16, line));
+                            }
+                            println!("{:08x}", vec.len());
+                        }
+                        None => fail!("Failed to read: {}", path),
+                    }
+                } else {
+                    fail!("Failed to open: {}", path);
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "wait",
+            help: "Wait for programs run in the background with exec and a trailing & to exit\n    Usage: wait [pid]...\n    Without a PID, waits for all of them",
+            main: Box::new(|args: &Vec<String>| {
+                let mut pids = Vec::new();
+                for arg in args.get_slice(Some(1), None).iter() {
+                    if arg.is_empty() || !arg.chars().all(|c| c.is_digit(10)) {
+                        fail!("Invalid PID: {}", arg);
+                        return;
+                    }
+                    pids.push(arg.to_num());
+                }
+                if args.len() < 2 {
+                    pids = unsafe { (*application).jobs.iter().map(|&(pid, _)| pid).collect() };
+                }
+
+                let mut i = 0;
+                while context_pids().iter().any(|pid| pids.contains(pid)) {
+                    if interrupted!() {
+                        fail!("Interrupted");
+                        return;
+                    }
+                    unsafe { sys_yield() };
+                    cooperate(i);
+                    i += 1;
+                }
+                unsafe { (*application).jobs.retain(|&(pid, _)| !pids.contains(&pid)) };
+            }),
+        });
+
+        commands.push(Command {
+            name: "wc",
+            help: "Count the lines, words and bytes of files, or of the input\n    Usage: wc [-l] [-w] [-c] [file]...\n    -l, -w and -c only show the lines, words or bytes",
+            main: Box::new(|args: &Vec<String>| {
+                let mut show = (false, false, false);
+                let mut paths = Vec::new();
+                for arg in args.iter().skip(1) {
+                    match &arg[..] {
+                        "-l" => show.0 = true,
+                        "-w" => show.1 = true,
+                        "-c" => show.2 = true,
+                        _ => paths.push(arg.clone()),
+                    }
+                }
+                if !show.0 && !show.1 && !show.2 {
+                    show = (true, true, true);
+                }
+
+                let mut sources = Vec::new();
+                if paths.is_empty() {
+                    match take_input() {
+                        Some(input) => sources.push((String::new(), input)),
+                        None => {
+                            fail!("No file given");
+                            return;
+                        }
+                    }
+                } else {
+                    for path in paths.iter() {
+                        match read_file(path) {
+                            Some(string) => sources.push((path.clone(), string)),
+                            None => return,
+                        }
+                    }
+                }
+
+                let mut total = (0, 0, 0);
+                for &(ref path, ref string) in sources.iter() {
+                    let counts = (string.matches('\n').count(),
+                                  string.split_whitespace().count(),
+                                  string.len());
+                    println!("{}", wc_line(counts, show, path));
+                    total = (total.0 + counts.0, total.1 + counts.1, total.2 + counts.2);
+                }
+                if sources.len() > 1 {
+                    println!("{}", wc_line(total, show, "total"));
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "while",
+            help: "Run the commands up to the matching done for as long as the comparison holds\n    Usage: while <left> <comparison> <right>\n    Comparisons are ==, !=, >, >=, < and <=",
+            main: Box::new(|_: &Vec<String>| {}),
+        });
+
+        commands.push(Command {
+            name: "windows",
+            help: "Print information about the open windows, or a single one\n    Usage: windows [id]",
+            main: Box::new(|args: &Vec<String>| {
+                let path = "orbital://info/".to_string() + args.get(1).map_or("", |id| &id[..]);
+                if let Some(mut file) = File::open(&path) {
+                    let mut string = String::new();
+                    match file.read_to_string(&mut string) {
+                        Some(_) => print!("{}", string),
+                        None => fail!("Failed to read: {}", path),
+                    }
+                } else {
+                    fail!("No such window: {}", path);
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "wget",
+            help: "Download a URL with HTTP GET and save the body of the response\n    Usage: wget [-t seconds] <url>\n    The URL is http://host[:port]/path, saved under the last part of the path\n    Redirects are followed, up to 5 of them\n    Fails without saving anything if the status is not 200 OK\n    -t gives up between reads once the timeout has passed, by default $TIMEOUT or 30 seconds\n    A read that never returns still blocks, so a connection that stops sending can still hang",
+            main: Box::new(|args: &Vec<String>| {
+                let (timeout, args) = timeout_arg(args);
+
+                let url = match args.get(1) {
+                    Some(arg) => match NetUrl::parse(arg) {
+                        Ok(ref url) if url.scheme != "http" => {
+                            fail!("Unsupported scheme: {}", url.scheme);
+                            return;
+                        }
+                        Ok(url) => url,
+                        Err(err) => {
+                            fail!("{}", err);
+                            return;
+                        }
+                    },
+                    None => {
+                        fail!("No url given");
+                        return;
+                    }
+                };
+
+                let mut location = url.clone();
+                let mut redirects = 0;
+                let response;
+                loop {
+                    let redirect = match http_get(&location, timeout) {
+                        Ok(redirect) => redirect,
+                        Err(error) => {
+                            fail!("{}", error);
+                            return;
+                        }
+                    };
+
+                    match redirect.status {
+                        301 | 302 | 303 | 307 | 308 => (),
+                        _ => {
+                            response = redirect;
+                            break;
+                        }
+                    }
+
+                    if redirects == REDIRECT_MAX {
+                        fail!("Stopped after {} redirects", REDIRECT_MAX);
+                        return;
+                    }
+                    redirects += 1;
+
+                    let next = redirect.header("Location").map(|next| location.join(next));
+                    location = match next {
+                        Some(Ok(next)) => next,
+                        Some(Err(error)) => {
+                            fail!("Invalid redirect: {}", error);
+                            return;
+                        }
+                        None => {
+                            fail!("HTTP {} {} without a Location", redirect.status, redirect.reason);
+                            return;
+                        }
+                    };
+                    if location.scheme != "http" {
+                        fail!("Unsupported scheme in redirect: {}", location.scheme);
+                        return;
+                    }
+                    println!("Redirected to http://{}:{}{}", location.host, location.port, location.target());
+                }
+
+                if response.status != 200 {
+                    fail!("HTTP {} {}", response.status, response.reason);
+                    return;
+                }
+
+                let name = url.file_name();
+                match File::create(&name) {
+                    Some(mut file) => if file.write(&response.body).is_none() {
+                        fail!("Failed to write: {}", name);
+                    },
+                    None => fail!("Failed to create: {}", name),
+                }
+            }),
+        });
+
+        commands.push(Command {
+            name: "help",
+            help: "List the commands, or describe one command\n    Usage: help [command]",
+            main: Box::new(|args: &Vec<String>| {
+                let registry = unsafe { &(*application).commands };
+                if let Some(name) = args.get(1) {
+                    match registry.get(name) {
+                        Some(command) => println!("{}", command.help),
+                        None => println!("Unknown command: {}", name),
+                    }
+                } else {
+                    let width = registry.iter().fold(0, |width, command| cmp::max(width, command.name.len())) + 2;
+                    for command in registry.iter() {
+                        let mut line = command.name.to_string();
+                        while line.len() < width {
+                            line.push(' ');
+                        }
+                        println!("{}{}", line, command.help.lines().next().unwrap_or(""));
+                    }
+                }
+            }),
+         });
 
         commands
     }
-}
+}
+
+/// The set of commands known to the shell, looked up by name
+/// Commands can be registered at startup, in addition to the builtins
+pub struct CommandRegistry<'a> {
+    commands: BTreeMap<String, Command<'a>>,
+}
+
+impl<'a> CommandRegistry<'a> {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        CommandRegistry {
+            commands: BTreeMap::new(),
+        }
+    }
+
+    /// Create a registry with the builtin commands
+    pub fn builtins() -> Self {
+        let mut registry = CommandRegistry::new();
+        for command in Command::vec() {
+            registry.register(command);
+        }
+        registry
+    }
+
+    /// Register a command, replacing any command with the same name
+    pub fn register(&mut self, command: Command<'a>) {
+        self.commands.insert(command.name.to_string(), command);
+    }
+
+    /// Find a command by name
+    pub fn get(&self, name: &str) -> Option<&Command<'a>> {
+        self.commands.get(name)
+    }
+
+    /// Iterate over the commands, sorted by name
+    pub fn iter(&self) -> btree_map::Values<String, Command<'a>> {
+        self.commands.values()
+    }
+
+    /// Find the command closest to a mistyped name, if one is within two edits
+    pub fn suggest(&self, name: &str) -> Option<&'a str> {
+        let mut best: Option<(usize, &'a str)> = None;
+        for command in self.iter() {
+            let distance = edit_distance(name, command.name);
+            if distance <= 2 && distance < command.name.len() &&
+               best.map_or(true, |(best_distance, _)| distance < best_distance) {
+                best = Some((distance, command.name));
+            }
+        }
+        best.map(|(_, name)| name)
+    }
+}
+
+/// The Levenshtein distance between two strings, counted in characters
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..b.len() + 1).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for j in 0..b.len() {
+            let above = row[j + 1];
+            row[j + 1] = if a_char == b[j] {
+                diagonal
+            } else {
+                1 + cmp::min(diagonal, cmp::min(above, row[j]))
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Split a line on the operators, outside of quotes and `$(...)` and not escaped
+/// Returns every part, with the operator before it, or an empty string for the first
+/// Operators that start with another operator have to come first
+pub fn split_operators(line: &str, operators: &[&'static str]) -> Vec<(&'static str, String)> {
+    let mut parts = Vec::new();
+    let mut operator = "";
+    let mut part = String::new();
+    let mut quote = None;
+    let mut depth = 0;
+    let mut skip = 0;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        if skip > 0 {
+            skip -= 1;
+            continue;
+        }
+
+        if escaped {
+            escaped = false;
+            part.push(c);
+            continue;
+        }
+
+        if let Some(open) = quote {
+            if c == open {
+                quote = None;
+            } else if c == '\\' && open == '"' {
+                escaped = true;
+            }
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '\'' || c == '"' {
+            quote = Some(c);
+        } else if c == '(' && (depth > 0 || line[.. i].ends_with('$')) {
+            depth += 1;
+        } else if c == ')' && depth > 0 {
+            depth -= 1;
+        } else if depth == 0 {
+            if let Some(found) = operators.iter().find(|found| line[i ..].starts_with(**found)) {
+                parts.push((operator, part));
+                operator = *found;
+                part = String::new();
+                skip = found.len() - 1;
+                continue;
+            }
+        }
+
+        part.push(c);
+    }
+    parts.push((operator, part));
+
+    parts
+}
+
+/// The command before a trailing `&`, if the line ends with one
+pub fn background(line: &str) -> Option<&str> {
+    let line = line.trim_right();
+    let parts = split_operators(line, &["&&", "&"]);
+    match parts.last() {
+        Some(&("&", ref part)) if part.trim().is_empty() => Some(&line[.. line.len() - 1]),
+        _ => None,
+    }
+}
+
+/// Split a command line into the commands of a pipeline, on `|`
+/// An empty command is a syntax error
+pub fn split_pipeline(line: &str) -> Result<Vec<String>, String> {
+    let stages: Vec<String> = split_operators(line, &["|"]).into_iter()
+                                                           .map(|(_, stage)| stage.trim().to_string())
+                                                           .collect();
+
+    if stages.len() > 1 && stages.iter().any(|stage| stage.is_empty()) {
+        return Err("Syntax error: empty command in pipeline".to_string());
+    }
+    Ok(stages)
+}
+
+/// Read a substitution after its `$(`, up to the matching `)`, returning what is in it
+/// Parentheses in quotes or escaped do not count
+pub fn read_substitution<I: Iterator<Item = char>>(chars: &mut I) -> Result<String, String> {
+    let mut inner = String::new();
+    let mut depth = 1;
+    let mut quote = None;
+    while let Some(c) = chars.next() {
+        if let Some(open) = quote {
+            if c == open {
+                quote = None;
+            } else if c == '\\' && open == '"' {
+                inner.push(c);
+                match chars.next() {
+                    Some(c) => inner.push(c),
+                    None => break,
+                }
+                continue;
+            }
+        } else if c == '\\' {
+            inner.push(c);
+            match chars.next() {
+                Some(c) => inner.push(c),
+                None => break,
+            }
+            continue;
+        } else if c == '\'' || c == '"' {
+            quote = Some(c);
+        } else if c == '(' {
+            depth += 1;
+        } else if c == ')' {
+            depth -= 1;
+            if depth == 0 {
+                return Ok(inner);
+            }
+        }
+        inner.push(c);
+    }
+    Err("Syntax error: $( without a closing )".to_string())
+}
+
+/// Remove the unquoted redirections from `words`, returning the contents of the file input
+/// is redirected from, and the path and file output is redirected to
+/// The output file is opened here, so it is created even if the command fails
+pub fn redirect(words: &mut Vec<Word>) -> Result<(Option<String>, Option<(String, File)>), String> {
+    let mut input_path = None;
+    let mut output_path = None;
+    let mut i = 0;
+    while i < words.len() {
+        if words[i].quoted {
+            i += 1;
+            continue;
+        }
+
+        let text = words[i].text.clone();
+        if text.starts_with("2>") {
+            return Err("Redirecting errors is not supported".to_string());
+        } else if text == "<" || text == ">" || text == ">>" {
+            words.remove(i);
+            if i >= words.len() {
+                return Err(format!("No file given after {}", text));
+            }
+            let path = words.remove(i).text;
+            if text == "<" {
+                input_path = Some(path);
+            } else {
+                output_path = Some((path, text == ">>"));
+            }
+        } else if text.starts_with('<') {
+            words.remove(i);
+            input_path = Some(text[1 ..].to_string());
+        } else if text.starts_with(">>") {
+            words.remove(i);
+            output_path = Some((text[2 ..].to_string(), true));
+        } else if text.starts_with('>') {
+            words.remove(i);
+            output_path = Some((text[1 ..].to_string(), false));
+        } else {
+            i += 1;
+        }
+    }
+
+    //Input is read first, so a missing input file does not empty the output file
+    let input = match input_path {
+        Some(path) => {
+            let mut input = String::new();
+            match File::open(&path) {
+                Some(mut file) => if file.read_to_string(&mut input).is_none() {
+                    return Err(format!("Failed to read: {}", path));
+                },
+                None => return Err(format!("No such file: {}", path)),
+            }
+            Some(input)
+        }
+        None => None,
+    };
+
+    let output = match output_path {
+        Some((path, append)) => match open_output(&path, append) {
+            Some(file) => Some((path, file)),
+            None => return Err(format!("Failed to open file for writing: {}", path)),
+        },
+        None => None,
+    };
+
+    Ok((input, output))
+}
+
+/// Does the line end with a backslash, escaping what comes after it?
+/// A line read from the console or a script continues on the next line if it does
+pub fn continues(line: &str) -> bool {
+    line.chars().rev().take_while(|&c| c == '\\').count() % 2 == 1
+}
+
+/// Can the character be part of a variable name?
+pub fn is_name_char(c: char) -> bool {
+    (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || (c >= '0' && c <= '9') || c == '_'
+}
+
+/// Quote text so that `tokenize` reads it back as a single word with the same text
+/// Text that needs no quotes, such as names and paths, is left as it is
+pub fn quote(text: &str) -> String {
+    if !text.is_empty() && text.chars().all(|c| is_name_char(c) || "/.,:+-=@%".contains(c)) {
+        return text.to_string();
+    }
+
+    let mut quoted = "'".to_string();
+    for c in text.chars() {
+        if c == '\'' {
+            quoted.push_str("'\\''");
+        } else {
+            quoted.push(c);
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// Does the command take comparisons as arguments, so `<` and `>` are not redirections?
+pub fn is_comparison(cmd: &str) -> bool {
+    cmd == "if" || cmd == "elif" || cmd == "assert"
+}
+
+/// Is the command run even in a branch that is not taken, to keep track of conditions and
+/// loops?
+pub fn is_control(cmd: &str) -> bool {
+    cmd == "if" || cmd == "elif" || cmd == "else" || cmd == "fi" || cmd == "done" || cmd == "reset"
+}
+
+/// Open a file to redirect output to, emptying it, or appending to it if `append` is set
+pub fn open_output(path: &str, append: bool) -> Option<File> {
+    if append {
+        if let Some(mut file) = File::open(path) {
+            if file.seek(SeekFrom::End(0)).is_some() {
+                return Some(file);
+            }
+            return None;
+        }
+    }
+    File::create(path)
+}
+
+/// Take the input of the running command, redirected from a file or piped from the
+/// previous command, if it has any
+pub fn take_input() -> Option<String> {
+    unsafe { (*application).input.take() }
+}
+
+/// Parse the `-n count` flag of `head` and `tail`, returning the count, 10 by default,
+/// and the file, if one was given
+pub fn line_count_args(args: &Vec<String>) -> Result<(usize, Option<String>), String> {
+    let mut count = 10;
+    let mut path = None;
+    let mut i = 1;
+    while let Some(arg) = args.get(i) {
+        if arg == "-n" {
+            let value = match args.get(i + 1) {
+                Some(value) => value,
+                None => return Err("No line count given".to_string()),
+            };
+            if value.is_empty() || !value.chars().all(|c| c.is_digit(10)) {
+                return Err(format!("Invalid line count: {}", value));
+            }
+            count = value.to_num();
+            i += 1;
+        } else {
+            path = Some(arg.clone());
+        }
+        i += 1;
+    }
+    Ok((count, path))
+}
+
+/// Read the file given to a filter, or else take its input
+pub fn file_or_input(path: Option<String>) -> Option<String> {
+    match path {
+        Some(path) => read_file(&path),
+        None => {
+            let input = take_input();
+            if input.is_none() {
+                fail!("No file given");
+            }
+            input
+        }
+    }
+}
+
+/// Format the line, word and byte counts of `wc`, only showing the chosen ones
+/// Counts of a file are lined up in columns, followed by its name; counts of the input
+/// are only separated by spaces, so they are easy to use in a script
+pub fn wc_line(counts: (usize, usize, usize), show: (bool, bool, bool), name: &str) -> String {
+    let mut shown = Vec::new();
+    if show.0 {
+        shown.push(counts.0);
+    }
+    if show.1 {
+        shown.push(counts.1);
+    }
+    if show.2 {
+        shown.push(counts.2);
+    }
+
+    let mut line = String::new();
+    for count in shown.iter() {
+        if name.is_empty() {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&format!("{}", count));
+        } else {
+            line.push_str(&format!("{:>8}", count));
+        }
+    }
+    if !name.is_empty() {
+        line = line + " " + name;
+    }
+    line
+}
+
+/// Read a whole file, printing an error and setting ? to 1 if it cannot be read
+pub fn read_file(path: &str) -> Option<String> {
+    let mut string = String::new();
+    match File::open(path) {
+        Some(mut file) => if file.read_to_string(&mut string).is_none() {
+            fail!("Failed to read: {}", path);
+            return None;
+        },
+        None => {
+            fail!("Failed to open file: {}", path);
+            return None;
+        }
+    }
+    Some(string)
+}
+
+/// Run each line of a script as a command, stopping at the first failed assert if `stop` is set
+/// The arguments are set in the variables `1`, `2`, ... while it runs
+/// Returns false if the script could not be read or an assert failed
+pub fn run_script(path: &str, args: &[String], stop: bool) -> bool {
+    let mut commands = String::new();
+    match File::open(path) {
+        Some(mut file) => if file.read_to_string(&mut commands).is_none() {
+            println!("Failed to read: {}", path);
+            return false;
+        },
+        None => {
+            println!("Failed to open file: {}", path);
+            return false;
+        }
+    }
+
+    let mut outer_args = Vec::new();
+    for (i, arg) in args.iter().enumerate() {
+        let name = format!("{}", i + 1);
+        unsafe {
+            outer_args.push((*application).get_var(&name));
+            (*application).set_var(&name, arg);
+        }
+    }
+
+    let failures = unsafe { (*application).failures };
+    let mut command = String::new();
+    for (i, line) in commands.split('\n').enumerate() {
+        if interrupted!() {
+            break;
+        }
+        cooperate(i);
+
+        if continues(line) {
+            command.push_str(&line[.. line.len() - 1]);
+            continue;
+        }
+        command.push_str(line);
+        exec!(command);
+        command.clear();
+
+        if stop && unsafe { (*application).failures } != failures {
+            println!("{}:{}: stopped", path, i + 1);
+            break;
+        }
+    }
+
+    unsafe {
+        if let Some(reading) = (*application).reading_loop.take() {
+            println!("{}: Syntax error: {} without done", path, reading.kind.keyword());
+        }
+        for (i, outer_arg) in outer_args.iter().enumerate() {
+            (*application).set_var(&format!("{}", i + 1), outer_arg.as_ref().map_or("", |arg| &arg[..]));
+        }
+        (*application).failures == failures
+    }
+}
+
+/// Run the script given to `source` or `.`
+pub fn source(args: &Vec<String>) {
+    match args.get(1) {
+        Some(path) => if !run_script(path, args.get_slice(Some(2), None), false) {
+            unsafe {
+                (*application).set_var("?", "1");
+            }
+        },
+        None => fail!("No script given"),
+    }
+}
+
+/// Run every `.sh` script in a directory, ending with a slash, and report how many passed
+/// Returns true if any of them failed
+pub fn run_dir(dir: &str, stop: bool) -> bool {
+    let mut scripts: Vec<String> = match read_dir(dir) {
+        Some(entries) => entries.map(|entry| entry.path().to_string())
+                                .filter(|name| name.ends_with(".sh"))
+                                .collect(),
+        None => Vec::new(),
+    };
+    scripts.sort();
+
+    let mut passed = 0;
+    let mut failed = 0;
+    for script in scripts.iter() {
+        if interrupted!() {
+            break;
+        }
+
+        if run_script(&(dir.to_string() + script), &[], stop) {
+            println!("{}: ok", script);
+            passed += 1;
+        } else {
+            println!("{}: FAILED", script);
+            failed += 1;
+        }
+    }
+
+    println!("{} passed, {} failed", passed, failed);
+    failed > 0
+}
+
+/// The kind of loop a line starts, with the rest of the line
+pub fn loop_start(line: &str) -> Option<LoopKind> {
+    let line = line.trim();
+    if line == "while" || line.starts_with("while ") {
+        Some(LoopKind::While(line[5 ..].trim().to_string()))
+    } else if line == "for" || line.starts_with("for ") {
+        Some(LoopKind::For(line[3 ..].trim().to_string()))
+    } else {
+        None
+    }
+}
+
+/// Does the line have a quote that is not closed?
+pub fn unterminated_quote(line: &str) -> bool {
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(open) => if c == open {
+                quote = None;
+            } else if c == '\\' && open == '"' {
+                chars.next();
+            },
+            None => if c == '\'' || c == '"' {
+                quote = Some(c);
+            } else if c == '\\' {
+                chars.next();
+            },
+        }
+    }
+    quote.is_some()
+}
+
+/// Evaluate a comparison, `<left> <comparison> <right>`, as used by `if` and `assert`
+/// The comparisons are `==` and `!=` on text, and `>`, `>=`, `<` and `<=` on numbers
+/// A file test, `-e`, `-f` or `-d` and a path, checks that the path exists, is a file or
+/// is a directory
+pub fn compare(args: &[String]) -> Result<bool, String> {
+    if args.len() == 2 {
+        return file_test(&args[0], &args[1]);
+    }
+
+    let left = match args.get(0) {
+        Some(left) => left,
+        None => return Err("No left hand side".to_string()),
+    };
+    let cmp = match args.get(1) {
+        Some(cmp) => cmp,
+        None => return Err("No comparison operator".to_string()),
+    };
+    let right = match args.get(2) {
+        Some(right) => right,
+        None => return Err("No right hand side".to_string()),
+    };
+
+    match &cmp[..] {
+        "==" => Ok(*left == *right),
+        "!=" => Ok(*left != *right),
+        ">" => Ok(left.to_num_signed() > right.to_num_signed()),
+        ">=" => Ok(left.to_num_signed() >= right.to_num_signed()),
+        "<" => Ok(left.to_num_signed() < right.to_num_signed()),
+        "<=" => Ok(left.to_num_signed() <= right.to_num_signed()),
+        _ => Err(format!("Unknown comparison: {}", cmp)),
+    }
+}
+
+/// Evaluate the expression of `test` or `[`
+/// Strings are compared with `=` and `!=`, and tested for being empty with `-z` or not with
+/// `-n`; integers are compared with `-eq`, `-ne`, `-lt`, `-gt`, `-le` and `-ge`; paths are
+/// tested with `-e`, `-f` and `-d`. A lone string is true if it is not empty
+pub fn test_expr(args: &[String]) -> Result<bool, String> {
+    match args.len() {
+        0 => Ok(false),
+        1 => Ok(!args[0].is_empty()),
+        2 => match &args[0][..] {
+            "-z" => Ok(args[1].is_empty()),
+            "-n" => Ok(!args[1].is_empty()),
+            "-e" | "-f" | "-d" => file_test(&args[0], &args[1]),
+            op => Err(format!("Unknown unary operator: {}", op)),
+        },
+        3 => {
+            let (left, op, right) = (&args[0], &args[1][..], &args[2]);
+            match op {
+                "=" | "==" => return Ok(left == right),
+                "!=" => return Ok(left != right),
+                _ => (),
+            }
+
+            let left = try!(arith::number(left));
+            let right = try!(arith::number(right));
+            match op {
+                "-eq" => Ok(left == right),
+                "-ne" => Ok(left != right),
+                "-lt" => Ok(left < right),
+                "-gt" => Ok(left > right),
+                "-le" => Ok(left <= right),
+                "-ge" => Ok(left >= right),
+                _ => Err(format!("Unknown binary operator: {}", op)),
+            }
+        }
+        _ => Err("Too many arguments to test".to_string()),
+    }
+}
+
+/// Run `test` or `[`, setting ? to 0 if the expression is true, 1 if it is false, or 2 if
+/// it is invalid
+pub fn test_command(args: &[String]) {
+    let status = match test_expr(args) {
+        Ok(true) => "0",
+        Ok(false) => "1",
+        Err(error) => {
+            println!("{}", error);
+            "2"
+        }
+    };
+    unsafe {
+        (*application).set_var("?", status);
+    }
+}
+
+/// Test a path with `-e` for existence, `-f` for a file or `-d` for a directory
+pub fn file_test(test: &str, path: &str) -> Result<bool, String> {
+    let file = !path.ends_with('/') && File::open(path).is_some();
+    let dir = !file && !path.is_empty() && read_dir(path).is_some();
+
+    match test {
+        "-e" => Ok(file || dir),
+        "-f" => Ok(file),
+        "-d" => Ok(dir),
+        _ => Err(format!("Unknown file test: {}", test)),
+    }
+}
+
+/// Check the structure of a script without running any commands
+/// Returns each error with its line number, counting from 1
+pub fn check_syntax(script: &str) -> Vec<(usize, String)> {
+    let mut errors = Vec::new();
+    // The open ifs and loops, with their line numbers
+    let mut blocks: Vec<(&'static str, usize)> = Vec::new();
+
+    for (i, line) in script.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+
+        if unterminated_quote(line) {
+            errors.push((line_number, "Unterminated quote".to_string()));
+        }
+
+        for (_, command) in split_operators(line, &[";"]) {
+            let innermost = blocks.last().map_or("", |&(keyword, _)| keyword);
+            match command.trim().split(' ').next().unwrap_or("") {
+                "if" => blocks.push(("if", line_number)),
+                "while" => blocks.push(("while", line_number)),
+                "for" => blocks.push(("for", line_number)),
+                "elif" => if innermost != "if" {
+                    errors.push((line_number, "elif found with no previous if".to_string()));
+                },
+                "else" => if innermost != "if" {
+                    errors.push((line_number, "else found with no previous if".to_string()));
+                },
+                "fi" => if innermost == "if" {
+                    blocks.pop();
+                } else {
+                    errors.push((line_number, "fi found with no previous if".to_string()));
+                },
+                "done" => if innermost == "while" || innermost == "for" {
+                    blocks.pop();
+                } else {
+                    errors.push((line_number, "done found with no previous loop".to_string()));
+                },
+                _ => (),
+            }
+        }
+    }
+
+    for (keyword, line_number) in blocks {
+        if keyword == "if" {
+            errors.push((line_number, "if without fi".to_string()));
+        } else {
+            errors.push((line_number, format!("{} without done", keyword)));
+        }
+    }
+
+    errors
+}
+
+/// Send a HTTP GET request for a URL and read the whole response
+pub fn http_get(url: &NetUrl, timeout: Duration) -> Result<HttpResponse, String> {
+    let mut con = match File::open(&url.socket()) {
+        Some(con) => con,
+        None => return Err(format!("Failed to connect: {}", url.socket())),
+    };
+
+    let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+                          url.target(),
+                          url.host);
+    if con.write(request.as_bytes()).is_none() {
+        return Err(format!("Failed to write to {}", url.socket()));
+    }
+
+    let mut data = Vec::new();
+    match net::read_timeout(&mut con, &mut data, timeout, &SystemClock) {
+        Some(true) => HttpResponse::parse(&data),
+        Some(false) => Err(format!("Timed out after {} seconds", timeout.secs)),
+        None => Err(format!("Failed to read from {}", url.socket())),
+    }
+}
+
+/// Take the `-t seconds` timeout flag out of the arguments of a network command
+/// Without the flag, the timeout comes from the `TIMEOUT` variable, or is 30 seconds
+pub fn timeout_arg(args: &Vec<String>) -> (Duration, Vec<String>) {
+    let mut secs = unsafe { (*application).get_var("TIMEOUT") }
+                       .map_or(30, |value| value.to_num() as i64);
+
+    let mut rest = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "-t" && i + 1 < args.len() {
+            secs = args[i + 1].to_num() as i64;
+            i += 2;
+        } else {
+            rest.push(args[i].clone());
+            i += 1;
+        }
+    }
+
+    (Duration::new(secs, 0), rest)
+}
+
+/// How many redirects `wget` follows before giving up
+pub const REDIRECT_MAX: usize = 5;
+
+/// The most elements `mapfile` and `read -a` store
+pub const ARRAY_MAX: usize = 4096;
+
+/// The directory `cd` goes to without an argument, if `HOME` is not set
+pub const HOME: &'static str = "file:/home/";
+
+/// The file the commands entered at the prompt are kept in between sessions
+pub const HISTORY_FILE: &'static str = "file:/home/.history";
+
+/// How deep `cp -r` goes into nested directories before giving up
+pub const COPY_DEPTH: usize = 32;
+
+/// How deep `find` goes into nested directories before giving up
+pub const FIND_DEPTH: usize = 32;
+
+/// How deep `rm -r` goes into nested directories before giving up
+pub const REMOVE_DEPTH: usize = 32;
+
+/// The PIDs listed by `context:`, including those of programs that have exited but are
+/// not removed yet
+pub fn context_pids() -> Vec<usize> {
+    let mut string = String::new();
+    if let Some(mut file) = File::open("context:") {
+        file.read_to_string(&mut string);
+    }
+
+    string.lines()
+          .skip(1)
+          .filter_map(|line| line.split_whitespace().next())
+          .filter(|pid| pid.chars().all(|c| c.is_digit(10)))
+          .map(|pid| pid.to_num())
+          .collect()
+}
+
+/// Find a program to run, searching the directories in `PATH` if the name has no `/`
+pub fn find_program(name: &str) -> Option<String> {
+    if name.contains('/') {
+        return Some(name.to_string());
+    }
+
+    let path = unsafe { (*application).get_var("PATH") }.unwrap_or(String::new());
+    if path.is_empty() {
+        return File::open(name).map(|_| name.to_string());
+    }
+
+    for dir in path.split(':').filter(|dir| !dir.is_empty()) {
+        let program = dir_path(dir) + name;
+        if File::open(&program).is_some() {
+            return Some(program);
+        }
+    }
+    None
+}
+
+/// The size of a file in bytes, if it can be opened
+pub fn file_size(path: &str) -> Option<usize> {
+    File::open(path).and_then(|mut file| file.seek(SeekFrom::End(0)))
+}
+
+/// Create a directory and any missing directories above it
+/// Returns the directory that could not be created if one fails
+pub fn create_dir_all(path: &str) -> Result<(), String> {
+    let path = path.trim_right_matches('/');
+    let mut ends: Vec<usize> = path.match_indices('/').map(|(i, _)| i).collect();
+    ends.push(path.len());
+
+    for end in ends {
+        let dir = &path[.. end];
+        //Skip the root, of the file system or of a scheme
+        if dir.is_empty() || dir.ends_with(':') || dir.ends_with('/') || is_dir(dir) {
+            continue;
+        }
+        if DirEntry::create(dir).is_none() && !is_dir(dir) {
+            return Err(dir.to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Is the path a directory?
+pub fn is_dir(path: &str) -> bool {
+    path.ends_with('/') || (File::open(path).is_none() && read_dir(path).is_some())
+}
+
+/// The path with a trailing slash, as directories are named
+pub fn dir_path(path: &str) -> String {
+    if path.ends_with('/') {
+        path.to_string()
+    } else {
+        path.to_string() + "/"
+    }
+}
+
+/// Copy a file, printing an error and returning false if it fails
+pub fn copy_file(src: &str, dst: &str) -> bool {
+    let mut data = Vec::new();
+    match File::open(src) {
+        Some(mut file) => if file.read_to_end(&mut data).is_none() {
+            println!("Failed to read: {}", src);
+            return false;
+        },
+        None => {
+            println!("Failed to open file: {}", src);
+            return false;
+        }
+    }
+
+    match File::create(dst) {
+        Some(mut file) => if file.write(&data).is_none() {
+            println!("Failed to write: {}", dst);
+            false
+        } else {
+            true
+        },
+        None => {
+            println!("Failed to create: {}", dst);
+            false
+        }
+    }
+}
+
+/// The full path of `path` from the directory `cwd`, with `.` and `..` resolved, so that
+/// two paths to the same file are equal
+pub fn canonical_path(cwd: &str, path: &str) -> String {
+    let full = if path.contains(':') {
+        path.to_string()
+    } else if path.starts_with('/') {
+        cwd[.. cwd.find(':').map_or(0, |i| i + 1)].to_string() + path
+    } else {
+        dir_path(cwd) + path
+    };
+
+    let root = full.find(':').map_or(0, |i| i + 1);
+    let mut parts: Vec<&str> = Vec::new();
+    for part in full[root ..].split('/') {
+        match part {
+            "" | "." => (),
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+
+    parts.iter().fold(full[.. root].to_string(), |path, part| path + "/" + part)
+}
+
+/// The path a file is copied or moved to, inside `dst` if it is a directory
+pub fn file_target(src: &str, dst: &str) -> String {
+    if dst.ends_with('/') || is_dir(dst) {
+        dir_path(dst) + src.rsplit('/').next().unwrap_or("")
+    } else {
+        dst.to_string()
+    }
+}
+
+/// Copy the directory `src` to `dst`, both ending with a slash, along with everything in it
+/// Errors are printed and the copy carries on; returns how many entries failed
+pub fn copy_dir(src: &str, dst: &str, depth: usize) -> usize {
+    if depth >= COPY_DEPTH {
+        println!("Too deeply nested, not copying: {}", src);
+        return 1;
+    }
+
+    if DirEntry::create(dst.trim_right_matches('/')).is_none() && read_dir(dst).is_none() {
+        println!("Failed to create {}", dst);
+        return 1;
+    }
+
+    let entries: Vec<String> = match read_dir(src) {
+        Some(dir) => dir.map(|entry| entry.path().to_string()).collect(),
+        //Empty directories can not be listed
+        None => return 0,
+    };
+
+    let mut errors = 0;
+    for (i, entry) in entries.iter().enumerate() {
+        if interrupted!() {
+            break;
+        }
+        cooperate(i);
+
+        if entry.ends_with('/') {
+            errors += copy_dir(&(src.to_string() + entry), &(dst.to_string() + entry), depth + 1);
+        } else if !copy_file(&(src.to_string() + entry), &(dst.to_string() + entry)) {
+            errors += 1;
+        }
+    }
+    errors
+}
+
+/// Print the paths in the directory `dir`, empty or ending with a slash, and in the
+/// directories in it, whose names match the wildcard `pattern`
+/// Returns how many were printed
+pub fn find_paths(dir: &str, pattern: &str, depth: usize) -> usize {
+    if depth >= FIND_DEPTH {
+        println!("Too deeply nested, not searching: {}", dir);
+        return 0;
+    }
+
+    let entries: Vec<String> = match read_dir(dir) {
+        Some(entries) => entries.map(|entry| entry.path().to_string()).collect(),
+        None => return 0,
+    };
+
+    let mut found = 0;
+    for (i, entry) in entries.iter().enumerate() {
+        if interrupted!() {
+            break;
+        }
+        cooperate(i);
+
+        let path = dir.to_string() + entry;
+        if glob_match(pattern, entry.trim_right_matches('/')) {
+            println!("{}", path);
+            found += 1;
+        }
+        if entry.ends_with('/') {
+            found += find_paths(&path, pattern, depth + 1);
+        }
+    }
+    found
+}
+
+/// Remove the directory `dir`, ending with a slash, and everything in it
+/// Errors are printed and the removal carries on; returns how many entries failed
+pub fn remove_tree(dir: &str, verbose: bool, depth: usize) -> usize {
+    if depth >= REMOVE_DEPTH {
+        println!("Too deeply nested, not removing: {}", dir);
+        return 1;
+    }
+
+    let entries: Vec<String> = match read_dir(dir) {
+        Some(entries) => entries.map(|entry| entry.path().to_string()).collect(),
+        None => Vec::new(),
+    };
+
+    let mut errors = 0;
+    for (i, entry) in entries.iter().enumerate() {
+        if interrupted!() {
+            return errors + 1;
+        }
+        cooperate(i);
+
+        let path = dir.to_string() + entry;
+        if entry.ends_with('/') {
+            errors += remove_tree(&path, verbose, depth + 1);
+        } else if remove_file(&path) {
+            if verbose {
+                println!("Removed {}", path);
+            }
+        } else {
+            println!("Failed to remove: {}", path);
+            errors += 1;
+        }
+    }
+
+    if remove_dir(dir) {
+        if verbose {
+            println!("Removed {}", dir);
+        }
+    } else {
+        println!("Failed to remove: {}", dir);
+        errors += 1;
+    }
+    errors
+}
+
+/// Is the path the root of a scheme, or the current or parent directory, which `rm`
+/// refuses to remove?
+pub fn is_protected(path: &str) -> bool {
+    let path = path.trim_right_matches('/');
+    path.is_empty() || path.ends_with(':') || path == "." || path == ".." ||
+    path.ends_with("/.") || path.ends_with("/..")
+}
+
+/// Expand a wildcard argument into the sorted paths that match it
+/// Arguments without wildcards, or without any match, are left as they are
+pub fn expand_glob(arg: &str) -> Vec<String> {
+    if !is_glob(arg) {
+        return vec![arg.to_string()];
+    }
+
+    let (dir, pattern) = match arg.rfind('/') {
+        Some(i) => (&arg[.. i + 1], &arg[i + 1 ..]),
+        None => ("", arg),
+    };
+
+    let mut paths = Vec::new();
+    if !is_glob(dir) {
+        if let Some(entries) = read_dir(dir) {
+            for entry in entries {
+                let name = entry.path().to_string();
+                if glob_match(pattern, name.trim_right_matches('/')) {
+                    paths.push(dir.to_string() + &name);
+                }
+            }
+        }
+    }
+
+    if paths.is_empty() {
+        paths.push(arg.to_string());
+    } else {
+        paths.sort();
+    }
+    paths
+}
+
+/// Print a partial line, such as a prompt, and flush it so it is shown before reading input
+/// Prints an error to stderr and returns false if stdout could not be written
+pub fn print_flush(text: &str) -> bool {
+    let mut out = stdout();
+    if out.write(text.as_bytes()).is_some() && out.flush() {
+        true
+    } else {
+        stderr().write(b"Failed to write to stdout\n");
+        false
+    }
+}
+
+/// Puts the console in raw mode, reading keys one at a time without echo, until dropped
+/// The console also leaves raw mode when the shell exits
+pub struct RawMode;
+
+impl RawMode {
+    pub fn enable() -> RawMode {
+        print_flush(RAW_MODE);
+        RawMode
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        print_flush(COOKED_MODE);
+    }
+}
+
+/// Format up to 16 bytes in the canonical hexdump layout:
+/// the offset, the bytes in hex, and the printable bytes as ASCII
+pub fn hex_line(offset: usize, bytes: &[u8]) -> String {
+    let mut line = format!("{:08x} ", offset);
+
+    for i in 0..16 {
+        if i == 8 {
+            line.push(' ');
+        }
+        match bytes.get(i) {
+            Some(b) => line = line + &format!(" {:02x}", b),
+            None => line = line + "   ",
+        }
+    }
+
+    line = line + "  |";
+    for b in bytes.iter() {
+        if *b >= 0x20 && *b < 0x7F {
+            line.push(*b as char);
+        } else {
+            line.push('.');
+        }
+    }
+    line.push('|');
+
+    line
+}
+
+/// Format a number of bytes with the largest unit of B, KiB, MiB or GiB that fits,
+/// with one decimal place
+pub fn human_size(bytes: u64) -> String {
+    let units = [("GiB", 1 << 30), ("MiB", 1 << 20), ("KiB", 1 << 10)];
+    for &(name, size) in units.iter() {
+        if bytes >= size {
+            let tenths = bytes * 10 / size;
+            return format!("{}.{} {}", tenths / 10, tenths % 10, name);
+        }
+    }
+    format!("{} B", bytes)
+}
+
+/// Quote text as a JSON string
+pub fn json_string(text: &str) -> String {
+    let mut quoted = "\"".to_string();
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Print words in columns that fit the width of the console
+pub fn print_columns(words: &[String]) {
+    let width = words.iter().fold(0, |width, word| cmp::max(width, word.chars().count())) + 2;
+    let per_line = cmp::max(1, console_size!().0 / width);
+
+    let mut line = String::new();
+    for (i, word) in words.iter().enumerate() {
+        line = line + word;
+        if (i + 1) % per_line == 0 || i + 1 == words.len() {
+            println!("{}", line);
+            line = String::new();
+        } else {
+            for _ in word.chars().count()..width {
+                line.push(' ');
+            }
+        }
+    }
+}
+
+/// Query the size of the console from the path of stdout, in character cells
+/// Falls back to 80x25 if stdout is not a console
+pub fn query_console_size() -> (usize, usize) {
+    if let Some(path) = stdout().path() {
+        //console:///cols/rows/title
+        if path.starts_with("console:///") {
+            let parts: Vec<&str> = path.split('/').collect();
+            if let (Some(cols), Some(rows)) = (parts.get(3), parts.get(4)) {
+                let cols = cols.to_num();
+                let rows = rows.to_num();
+                if cols > 0 && rows > 0 {
+                    return (cols, rows);
+                }
+            }
+        }
+    }
+
+    (80, 25)
+}
+
+/// The prompt used when `PS1` is not set
+pub const DEFAULT_PROMPT: &'static str = "\\u@\\h:\\w# ";
+
+/// Render a `PS1` prompt, replacing `\u` with the user name, `\h` with the host name,
+/// `\w` with the working directory and `\\` with a backslash
+/// Other escapes are kept as they are
+pub fn render_prompt(ps1: &str, directory: &str) -> String {
+    let mut prompt = String::new();
+    let mut chars = ps1.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            prompt.push(c);
+            continue;
+        }
+
+        match chars.peek() {
+            Some(&'u') => prompt.push_str("user"),
+            Some(&'h') => prompt.push_str("redox"),
+            Some(&'w') => prompt.push_str(directory),
+            Some(&'\\') => prompt.push('\\'),
+            _ => {
+                prompt.push('\\');
+                continue;
+            }
+        }
+        chars.next();
+    }
+    prompt
+}
+
+/// Interpret the backslash escapes `\n`, `\t`, `\r`, `\\`, `\0` and `\xNN` in a string
+/// A `\xNN` above `\x7f` is a Latin-1 character, not a raw byte
+pub fn unescape(string: &str) -> String {
+    let mut ret = String::new();
+
+    let mut chars = string.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            ret.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => ret.push('\n'),
+            Some('t') => ret.push('\t'),
+            Some('r') => ret.push('\r'),
+            Some('\\') => ret.push('\\'),
+            Some('0') => {
+                if chars.peek() == Some(&'x') {
+                    chars.next();
+                    ret.push(unescape_hex(&mut chars));
+                } else {
+                    ret.push('\0');
+                }
+            }
+            Some('x') => ret.push(unescape_hex(&mut chars)),
+            Some(other) => {
+                ret.push('\\');
+                ret.push(other);
+            }
+            None => ret.push('\\'),
+        }
+    }
+
+    ret
+}
+
+/// Read up to two hex digits following a `\x` escape
+/// The value is taken as Latin-1, so `\xff` is U+00FF, which is two bytes once written as UTF-8
+fn unescape_hex<I: Iterator<Item = char>>(chars: &mut Peekable<I>) -> char {
+    let mut value = 0;
+    for _ in 0..2 {
+        match chars.peek().and_then(|c| c.to_digit(16)) {
+            Some(digit) => value = value * 16 + digit,
+            None => break,
+        }
+        chars.next();
+    }
+    value as u8 as char
+}
+
+pub struct Mode {
+    /// Whether the commands of the current branch run
+    value: bool,
+    /// Whether any branch of the if has run, so later branches do not
+    matched: bool,
+}
+
+/// A word of a command line
+pub struct Word {
+    pub text: String,
+    /// Was any of the word quoted? Quoted words are not wildcards or redirections
+    pub quoted: bool,
+}
+
+/// What repeats a loop
+pub enum LoopKind {
+    /// `while <comparison>`, evaluated before every iteration
+    While(String),
+    /// `for <variable> in <words>...`, running once for every word
+    For(String),
+}
+
+impl LoopKind {
+    /// The keyword starting the loop
+    pub fn keyword(&self) -> &'static str {
+        match *self {
+            LoopKind::While(_) => "while",
+            LoopKind::For(_) => "for",
+        }
+    }
+}
+
+/// A loop, read up to its `done`
+pub struct Loop {
+    kind: LoopKind,
+    /// The commands between the start of the loop and `done`
+    body: Vec<String>,
+    /// How many loops nested in the body are not done yet
+    depth: usize,
+}
+
+/// An application
+pub struct Application<'a> {
+    commands: CommandRegistry<'a>,
+    /// The variables, by name
+    variables: BTreeMap<String, String>,
+    /// The aliases, by name, and the words they are replaced by
+    aliases: BTreeMap<String, String>,
+    /// The names of the variables listed by `env`
+    exported: BTreeSet<String>,
+    modes: Vec<Mode>,
+    /// The console size in columns and rows, refreshed at every prompt
+    console_size: (usize, usize),
+    /// Set when the user interrupts, cleared when back at the prompt
+    interrupted: Cell<bool>,
+    /// The input of the running command, if it was redirected from a file
+    input: Option<String>,
+    /// The commands entered at the prompt
+    history: Vec<String>,
+    /// How many assertions have failed
+    failures: usize,
+    /// The loop being read, if a `while` has not reached its `done` yet
+    reading_loop: Option<Loop>,
+    /// Set while running a command with a trailing &
+    background: bool,
+    /// The programs run in the background, by PID, with their command lines
+    jobs: Vec<(usize, String)>,
+}
+
+impl<'a> Application<'a> {
+    /// Create a new empty application
+    pub fn new() -> Self {
+        return Application {
+            commands: CommandRegistry::builtins(),
+            variables: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+            exported: BTreeSet::new(),
+            modes: Vec::new(),
+            console_size: query_console_size(),
+            interrupted: Cell::new(false),
+            input: None,
+            history: Vec::new(),
+            failures: 0,
+            reading_loop: None,
+            background: false,
+            jobs: Vec::new(),
+        };
+    }
+
+    fn on_command(&mut self, command_string: &str) {
+        //Interrupted, abort back to the prompt
+        if poll_interrupt() {
+            self.interrupted.set(true);
+        }
+        if self.interrupted.get() {
+            return;
+        }
+
+        //Comment
+        if command_string.trim_left().starts_with('#') {
+            return;
+        }
+
+        //Run commands separated by ; one after another, skipping empty ones
+        let commands = split_operators(command_string, &[";"]);
+        if commands.len() > 1 {
+            for &(_, ref command) in commands.iter() {
+                if !command.trim().is_empty() {
+                    self.on_command(command.trim());
+                }
+            }
+            return;
+        }
+
+        //A reset gets out of a loop that is missing its done, instead of being read into it
+        if self.reading_loop.is_some() && command_string.split_whitespace().next() == Some("reset") {
+            self.reading_loop = None;
+        }
+
+        //Collect the body of a loop, and run it once its done is read
+        if self.reading_loop.is_some() {
+            self.read_loop_line(command_string);
+            return;
+        }
+
+        if let Some(kind) = loop_start(command_string) {
+            self.reading_loop = Some(Loop {
+                kind: kind,
+                body: Vec::new(),
+                depth: 0,
+            });
+            return;
+        }
+
+        //Show variables
+        if command_string == "$" {
+            self.print_variables();
+            return;
+        }
+
+        //Programs run by exec are never waited for, a trailing & keeps their PID for jobs and
+        //wait. Builtins run inside the shell, so they can not be put in the background
+        if let Some(command) = background(command_string) {
+            if command.split_whitespace().next() != Some("exec") {
+                println!("Only programs run with exec can be put in the background: {}", command.trim());
+                self.set_var("?", "1");
+                return;
+            }
+            self.background = true;
+            self.on_command(command);
+            self.background = false;
+            return;
+        }
+
+        //Run commands joined by && and || from left to right, skipping those that the
+        //status of the last command run does not call for
+        let parts = split_operators(command_string, &["&&", "||"]);
+        if parts.len() > 1 && parts.iter().any(|&(_, ref part)| part.trim().is_empty()) {
+            println!("Syntax error: missing command next to && or ||");
+            self.set_var("?", "2");
+            return;
+        }
+
+        for &(operator, ref part) in parts.iter() {
+            if self.interrupted.get() {
+                break;
+            }
+
+            let success = self.get_var("?").map_or(true, |status| status == "0");
+            if (operator == "&&" && !success) || (operator == "||" && success) {
+                continue;
+            }
+            self.run_pipeline(part);
+        }
+    }
+
+    /// Run a pipeline, commands joined by `|`, each with the output of the previous one as
+    /// its input
+    fn run_pipeline(&mut self, command_string: &str) {
+        let stages = match split_pipeline(command_string) {
+            Ok(stages) => stages,
+            Err(error) => {
+                println!("{}", error);
+                self.set_var("?", "2");
+                return;
+            }
+        };
+
+        //Each stage but the last has its output captured, to be the input of the next
+        let mut input = None;
+        for (i, stage) in stages.iter().enumerate() {
+            let last = i + 1 == stages.len();
+            if !last {
+                begin_capture();
+            }
+            self.run_stage(stage, input.take());
+            if !last {
+                input = Some(String::from_utf8_lossy(&end_capture()).into_owned());
+            }
+        }
+    }
+
+    /// The output of a `$(command)`, without its trailing newlines, or the value of a
+    /// `$((expression))`, given what is between the outer parentheses
+    /// Substitutions in the command are replaced when it runs, so they can be nested
+    fn substitute(&mut self, inner: &str) -> Result<String, String> {
+        if inner.starts_with('(') && inner.ends_with(')') {
+            let value = try!(arith::eval(&inner[1 .. inner.len() - 1], &|name: &str| self.get_var(name)));
+            return Ok(format!("{}", value));
+        }
+
+        begin_capture();
+        self.on_command(inner);
+        let output = String::from_utf8_lossy(&end_capture()).into_owned();
+        Ok(output.trim_right_matches('\n').to_string())
+    }
+
+    /// Split a command into words, removing quotes and escapes and replacing variables and
+    /// substitutions
+    /// Single quotes keep what is in them as it is, double quotes still replace variables
+    /// and substitutions
+    /// A backslash escapes the next character, and in double quotes only `$`, `"` and `\`
+    /// The output of an unquoted substitution is split into words on whitespace, and is
+    /// never read as quotes, redirections or wildcards
+    /// An unquoted word that is left empty is dropped
+    fn tokenize(&mut self, command: &str) -> Result<Vec<Word>, String> {
+        let mut words = Vec::new();
+        let mut text = String::new();
+        let mut started = false;
+        let mut quoted = false;
+
+        let mut chars = command.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                ' ' | '\t' => {
+                    if started && (quoted || !text.is_empty()) {
+                        words.push(Word {
+                            text: text,
+                            quoted: quoted,
+                        });
+                        text = String::new();
+                    }
+                    started = false;
+                    quoted = false;
+                }
+                '\'' => {
+                    started = true;
+                    quoted = true;
+                    loop {
+                        match chars.next() {
+                            Some('\'') => break,
+                            Some(c) => text.push(c),
+                            None => return Err("Syntax error: unterminated '".to_string()),
+                        }
+                    }
+                }
+                '"' => {
+                    started = true;
+                    quoted = true;
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('\\') => match chars.peek() {
+                                Some(&'$') | Some(&'"') | Some(&'\\') => text.push(chars.next().unwrap()),
+                                _ => text.push('\\'),
+                            },
+                            Some('$') => if chars.peek() == Some(&'(') {
+                                chars.next();
+                                let inner = try!(read_substitution(&mut chars));
+                                text.push_str(&try!(self.substitute(&inner)));
+                            } else {
+                                try!(self.expand_var(&mut chars, &mut text));
+                            },
+                            Some(c) => text.push(c),
+                            None => return Err("Syntax error: unterminated \"".to_string()),
+                        }
+                    }
+                }
+                '\\' => {
+                    started = true;
+                    quoted = true;
+                    if let Some(c) = chars.next() {
+                        text.push(c);
+                    }
+                }
+                '$' if chars.peek() == Some(&'(') => {
+                    chars.next();
+                    let inner = try!(read_substitution(&mut chars));
+                    for c in try!(self.substitute(&inner)).chars() {
+                        if c == ' ' || c == '\t' || c == '\n' {
+                            if started && (quoted || !text.is_empty()) {
+                                words.push(Word {
+                                    text: text,
+                                    quoted: quoted,
+                                });
+                                text = String::new();
+                            }
+                            started = false;
+                            quoted = false;
+                        } else {
+                            started = true;
+                            quoted = true;
+                            text.push(c);
+                        }
+                    }
+                }
+                '$' => {
+                    started = true;
+                    try!(self.expand_var(&mut chars, &mut text));
+                }
+                _ => {
+                    started = true;
+                    text.push(c);
+                }
+            }
+        }
+
+        if started && (quoted || !text.is_empty()) {
+            words.push(Word {
+                text: text,
+                quoted: quoted,
+            });
+        }
+
+        Ok(words)
+    }
 
-/// A (env) variable
-pub struct Variable {
-    pub name: String,
-    pub value: String,
-}
+    /// Read the name of a variable after a `$`, or in braces as in `${name}`, and add its
+    /// value to `text`
+    /// An unset variable adds nothing, and a `$` without a name is kept
+    fn expand_var<I: Iterator<Item = char>>(&self, chars: &mut Peekable<I>, text: &mut String) -> Result<(), String> {
+        let mut name = String::new();
+        if chars.peek() == Some(&'{') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('}') => break,
+                    Some(c) => name.push(c),
+                    None => return Err("Syntax error: ${ without a closing }".to_string()),
+                }
+            }
+            if name.is_empty() {
+                return Err("Syntax error: ${} has no variable name".to_string());
+            }
+        } else if chars.peek() == Some(&'?') {
+            chars.next();
+            name.push('?');
+        } else {
+            while let Some(&c) = chars.peek() {
+                if !is_name_char(c) {
+                    break;
+                }
+                name.push(c);
+                chars.next();
+            }
+        }
 
-pub struct Mode {
-    value: bool,
-}
+        if name.is_empty() {
+            text.push('$');
+        } else if let Some(value) = self.get_var(&name) {
+            text.push_str(&value);
+        }
+        Ok(())
+    }
 
-/// An application
-pub struct Application<'a> {
-    commands: Vec<Command<'a>>,
-    variables: Vec<Variable>,
-    modes: Vec<Mode>,
-}
+    /// Split a command into arguments, printing a syntax error if it fails
+    fn args(&mut self, command: &str) -> Option<Vec<String>> {
+        match self.tokenize(command) {
+            Ok(words) => Some(words.into_iter().map(|word| word.text).collect()),
+            Err(error) => {
+                println!("{}", error);
+                self.set_var("?", "2");
+                None
+            }
+        }
+    }
 
-impl<'a> Application<'a> {
-    /// Create a new empty application
-    pub fn new() -> Self {
-        return Application {
-            commands: Command::vec(),
-            variables: Vec::new(),
-            modes: Vec::new(),
+    /// Add a line to the loop being read, running the loop if the line is its `done`
+    fn read_loop_line(&mut self, line: &str) {
+        let word = line.trim().split(' ').next().unwrap_or("");
+        let finished = match self.reading_loop {
+            Some(ref mut reading) => {
+                if word == "done" && reading.depth == 0 {
+                    true
+                } else {
+                    if word == "while" || word == "for" {
+                        reading.depth += 1;
+                    } else if word == "done" {
+                        reading.depth -= 1;
+                    }
+                    reading.body.push(line.to_string());
+                    false
+                }
+            }
+            None => false,
         };
+
+        if finished {
+            if let Some(reading) = self.reading_loop.take() {
+                self.run_loop(&reading);
+            }
+        }
     }
 
-    fn on_command(&mut self, command_string: &str) {
-        //Comment
-        if command_string.starts_with('#') {
+    /// Run the body of a loop, for as long as its condition holds or once for every word
+    fn run_loop(&mut self, running: &Loop) {
+        //A loop in a branch that is not taken does not run
+        if self.modes.iter().any(|mode| !mode.value) {
             return;
         }
 
-        //Show variables
-        if command_string == "$" {
-            for variable in self.variables.iter() {
-                println!("{}={}", variable.name, variable.value);
+        match running.kind {
+            LoopKind::While(ref condition) => {
+                let mut iteration = 0;
+                while !self.interrupted.get() {
+                    cooperate(iteration);
+                    iteration += 1;
+
+                    let args = match self.args(condition) {
+                        Some(args) => args,
+                        None => break,
+                    };
+                    match compare(&args) {
+                        Ok(true) => (),
+                        Ok(false) => break,
+                        Err(error) => {
+                            println!("{}", error);
+                            break;
+                        }
+                    }
+
+                    for line in running.body.iter() {
+                        self.on_command(line);
+                    }
+                }
+            }
+            LoopKind::For(ref list) => {
+                let list_words = match self.tokenize(list) {
+                    Ok(words) => words,
+                    Err(error) => {
+                        println!("{}", error);
+                        return;
+                    }
+                };
+                if list_words.len() < 2 || list_words[1].quoted || list_words[1].text != "in" {
+                    println!("Syntax error: expected for <variable> in <words>...");
+                    return;
+                }
+
+                //Unquoted words holding lists are split, and their wildcards are expanded
+                let mut words: Vec<String> = Vec::new();
+                for word in list_words.iter().skip(2) {
+                    if word.quoted {
+                        words.push(word.text.clone());
+                    } else {
+                        for part in word.text.split_whitespace() {
+                            words.extend(expand_glob(part).into_iter());
+                        }
+                    }
+                }
+                let name = list_words[0].text.clone();
+
+                for (i, word) in words.iter().enumerate() {
+                    if self.interrupted.get() {
+                        break;
+                    }
+                    cooperate(i);
+
+                    self.set_var(&name, word);
+                    for line in running.body.iter() {
+                        self.on_command(line);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Keep track of a command in a branch that is not taken without reading its words, so
+    /// it runs no substitutions and opens no files to redirect to
+    /// The condition of an if is only evaluated if the if is reached, and that of an elif
+    /// only if its if was reached and no branch of it was taken yet
+    /// Returns true if the command was skipped, or false if it has to run
+    fn skip_untaken(&mut self, command_string: &str) -> bool {
+        let args: Vec<String> = command_string.split_whitespace().map(|word| word.to_string()).collect();
+        let cmd = match args.get(0) {
+            Some(cmd) => cmd.clone(),
+            None => return false,
+        };
+
+        //An if that is not reached is pushed as already matched, so none of its branches
+        //are taken
+        if cmd == "elif" {
+            if self.modes.get(0).map_or(false, |mode| mode.matched) {
+                self.modes[0].value = false;
+                return true;
             }
+            return false;
+        }
+
+        if self.modes.iter().all(|mode| mode.value) {
+            return false;
+        }
+
+        if cmd == "if" {
+            self.modes.insert(0, Mode { value: false, matched: true });
+        } else if is_control(&cmd) {
+            self.execute(&args);
+        }
+        true
+    }
+
+    /// Run a single command of a pipeline, with the output of the previous command as input
+    fn run_stage(&mut self, command_string: &str, mut input: Option<String>) {
+        if self.skip_untaken(command_string) {
             return;
         }
 
-        //Explode into arguments, replace variables
-        let mut args: Vec<String> = Vec::<String>::new();
-        for arg in command_string.split(' ') {
-            if !arg.is_empty() {
-                if arg.starts_with('$') {
-                    let name = arg[1 .. arg.len()].to_string();
-                    for variable in self.variables.iter() {
-                        if variable.name == name {
-                            args.push(variable.value.clone());
-                            break;
-                        }
+        let mut words = match self.tokenize(command_string) {
+            Ok(words) => words,
+            Err(error) => {
+                println!("{}", error);
+                self.set_var("?", "2");
+                return;
+            }
+        };
+
+        //Replace an alias, only once so an alias can refer to a command of the same name
+        let alias = match words.get(0) {
+            Some(word) if !word.quoted => self.aliases.get(&word.text).map(|words| words.clone()),
+            _ => None,
+        };
+        if let Some(alias) = alias {
+            match self.tokenize(&alias) {
+                Ok(alias_words) => {
+                    words.remove(0);
+                    for (i, word) in alias_words.into_iter().enumerate() {
+                        words.insert(i, word);
                     }
+                }
+                Err(error) => {
+                    println!("{}", error);
+                    self.set_var("?", "2");
+                    return;
+                }
+            }
+        }
+
+        //Redirect input and output and expand wildcards, unless comparing
+        //Quoted words are neither redirections nor wildcards
+        let mut output = None;
+        let args: Vec<String> = if words.get(0).map_or(false, |word| !is_comparison(&word.text)) {
+            match redirect(&mut words) {
+                Ok((Some(file_input), file_output)) => {
+                    input = Some(file_input);
+                    output = file_output;
+                }
+                Ok((None, file_output)) => output = file_output,
+                Err(error) => {
+                    println!("{}", error);
+                    self.set_var("?", "1");
+                    return;
+                }
+            }
+
+            let mut args = Vec::new();
+            for word in words {
+                if word.quoted {
+                    args.push(word.text);
                 } else {
-                    args.push(arg.to_string());
+                    args.extend(expand_glob(&word.text).into_iter());
+                }
+            }
+            args
+        } else {
+            words.into_iter().map(|word| word.text).collect()
+        };
+
+        if output.is_some() {
+            begin_capture();
+        }
+
+        let outer_input = mem::replace(&mut self.input, input);
+        self.execute(&args);
+        self.input = outer_input;
+
+        if let Some((path, mut file)) = output {
+            let data = end_capture();
+            if file.write(&data).is_none() {
+                println!("Failed to write: {}", path);
+            }
+        }
+    }
+
+    /// Read a line of input, from the redirected input if there is one
+    fn read_line(&mut self) -> Option<String> {
+        match self.input {
+            Some(ref mut input) => {
+                if input.is_empty() {
+                    return None;
                 }
+                let line = match input.find('\n') {
+                    Some(i) => input[.. i].to_string(),
+                    None => input.clone(),
+                };
+                let rest = input[cmp::min(input.len(), line.len() + 1) ..].to_string();
+                *input = rest;
+                Some(line)
             }
+            None => readln!(),
         }
+    }
 
-        //Execute commands
+    /// Execute a command that has been split into arguments
+    fn execute(&mut self, args: &Vec<String>) {
         if let Some(cmd) = args.get(0) {
             if cmd == "if" {
-                let mut value = false;
-
-                if let Some(left) = args.get(1) {
-                    if let Some(cmp) = args.get(2) {
-                        if let Some(right) = args.get(3) {
-                            if cmp == "==" {
-                                value = *left == *right;
-                            } else if cmp == "!=" {
-                                value = *left != *right;
-                            } else if cmp == ">" {
-                                value = left.to_num_signed() > right.to_num_signed();
-                            } else if cmp == ">=" {
-                                value = left.to_num_signed() >= right.to_num_signed();
-                            } else if cmp == "<" {
-                                value = left.to_num_signed() < right.to_num_signed();
-                            } else if cmp == "<=" {
-                                value = left.to_num_signed() <= right.to_num_signed();
-                            } else {
-                                println!("Unknown comparison: {}", cmp);
+                let value = match compare(args.get_slice(Some(1), None)) {
+                    Ok(value) => value,
+                    Err(error) => {
+                        println!("{}", error);
+                        false
+                    }
+                };
+
+                self.modes.insert(0, Mode { value: value, matched: value });
+                return;
+            }
+
+            if cmd == "elif" {
+                let mut syntax_error = false;
+                let already_matched = self.modes.get(0).map(|mode| mode.matched);
+                match already_matched {
+                    Some(true) => self.modes[0].value = false,
+                    Some(false) => {
+                        let value = match compare(args.get_slice(Some(1), None)) {
+                            Ok(value) => value,
+                            Err(error) => {
+                                println!("{}", error);
+                                false
                             }
-                        } else {
-                            println!("No right hand side");
-                        }
-                    } else {
-                        println!("No comparison operator");
+                        };
+                        self.modes[0] = Mode { value: value, matched: value };
                     }
-                } else {
-                    println!("No left hand side");
+                    None => syntax_error = true,
+                }
+                if syntax_error {
+                    println!("Syntax error: elif found with no previous if");
                 }
-
-                self.modes.insert(0, Mode { value: value });
                 return;
             }
 
             if cmd == "else" {
                 let mut syntax_error = false;
                 match self.modes.get_mut(0) {
-                    Some(mode) => mode.value = !mode.value,
+                    Some(mode) => {
+                        mode.value = !mode.matched;
+                        mode.matched = true;
+                    }
                     None => syntax_error = true,
                 }
                 if syntax_error {
@@ -453,18 +3634,153 @@ impl<'a> Application<'a> {
                 return;
             }
 
+            if cmd == "done" {
+                println!("Syntax error: done found with no previous loop");
+                return;
+            }
+
+            if cmd == "reset" {
+                self.modes.clear();
+                self.reading_loop = None;
+                if args.get(1).map_or(false, |arg| arg == "-v") {
+                    let exported = &self.exported;
+                    self.variables = self.variables
+                                         .iter()
+                                         .filter(|&(name, _)| exported.contains(name))
+                                         .map(|(name, value)| (name.clone(), value.clone()))
+                                         .collect();
+                    println!("Reset conditions and variables, except exported ones");
+                } else {
+                    println!("Reset conditions");
+                }
+                return;
+            }
+
             for mode in self.modes.iter() {
                 if !mode.value {
                     return;
                 }
             }
 
+            if cmd == "assert" {
+                let failure = match compare(args.get_slice(Some(1), None)) {
+                    Ok(true) => None,
+                    Ok(false) => {
+                        let mut condition = String::new();
+                        for arg in args.get_slice(Some(1), None) {
+                            condition = condition + " " + arg;
+                        }
+                        Some(format!("Assertion failed:{}", condition))
+                    }
+                    Err(error) => Some(format!("Assertion failed: {}", error)),
+                };
+
+                match failure {
+                    Some(message) => {
+                        println!("{}", message);
+                        self.failures += 1;
+                        self.set_var("?", "1");
+                    }
+                    None => self.set_var("?", "0"),
+                }
+                return;
+            }
+
+            if cmd == "read" && args.get(1).map_or(false, |arg| arg == "-a") {
+                if let Some(name) = args.get(2) {
+                    print_flush(&format!("{}=", name));
+                    if let Some(line) = self.read_line() {
+                        let words: Vec<String> = line.split_whitespace()
+                                                     .map(|word| word.to_string())
+                                                     .collect();
+                        self.set_array(name, &words);
+                    }
+                } else {
+                    println!("No array name given");
+                }
+                return;
+            }
+
+            if cmd == "read" && args.get(1).map_or(false, |arg| arg == "-k") {
+                if let Some(name) = args.get(2) {
+                    print_flush(&format!("{}=", name));
+                    //Redirected input gives its first character
+                    let redirected_key = match self.input {
+                        Some(ref mut input) => {
+                            let key = input.chars().next().map_or(String::new(), |c| c.to_string());
+                            *input = input[key.len() ..].to_string();
+                            Some(key)
+                        }
+                        None => None,
+                    };
+                    if let Some(key) = redirected_key {
+                        println!("");
+                        self.set_var(name, &key);
+                        return;
+                    }
+
+                    let mut buf = [0; 16];
+                    let count = {
+                        let _raw = RawMode::enable();
+                        stdin().read(&mut buf)
+                    };
+                    match count {
+                        Some(count) => {
+                            let key = String::from_utf8_lossy(&buf[.. count]).into_owned();
+                            println!("");
+                            self.set_var(name, &key);
+                        }
+                        None => println!("Failed to read from stdin"),
+                    }
+                } else {
+                    println!("No variable name given");
+                }
+                return;
+            }
+
+            if cmd == "mapfile" {
+                if let Some(name) = args.get(1) {
+                    let mut lines = Vec::new();
+                    if let Some(path) = args.get(2) {
+                        let mut string = String::new();
+                        match File::open(path) {
+                            Some(mut file) => if file.read_to_string(&mut string).is_none() {
+                                println!("Failed to read: {}", path);
+                                return;
+                            },
+                            None => {
+                                println!("Failed to open file: {}", path);
+                                return;
+                            }
+                        }
+                        lines = string.lines().map(|line| line.to_string()).collect();
+                    } else {
+                        //Read redirected input to its end, or the console up to an empty line
+                        while let Some(line) = self.read_line() {
+                            if self.input.is_none() && line.is_empty() {
+                                break;
+                            }
+                            lines.push(line);
+                        }
+                    }
+
+                    if lines.len() > ARRAY_MAX {
+                        println!("Only keeping the first {} of {} lines", ARRAY_MAX, lines.len());
+                        lines.truncate(ARRAY_MAX);
+                    }
+                    self.set_array(name, &lines);
+                } else {
+                    println!("No array name given");
+                }
+                return;
+            }
+
             if cmd == "read" {
                 for i in 1..args.len() {
                     if let Some(arg_original) = args.get(i) {
                         let arg = arg_original.trim();
-                        print!("{}=", arg);
-                        if let Some(value_original) = readln!() {
+                        print_flush(&format!("{}=", arg));
+                        if let Some(value_original) = self.read_line() {
                             let value = value_original.trim();
                             self.set_var(arg, value);
                         }
@@ -488,50 +3804,157 @@ impl<'a> Application<'a> {
             }
 
             //Commands
-            for command in self.commands.iter() {
-                if &command.name == cmd {
-                    (*command.main)(&args);
-                    return;
-                }
+            if let Some(command) = self.commands.get(cmd) {
+                self.set_var("?", "0");
+                (*command.main)(args);
+                return;
             }
 
+            self.set_var("?", "127");
             println!("Unknown command: '{}'", cmd);
+            if let Some(suggestion) = self.commands.suggest(cmd) {
+                println!("Did you mean '{}'?", suggestion);
+            }
         }
     }
 
 
-    pub fn set_var(&mut self, name: &str, value: &str){
-        if name.is_empty() {
+    /// Load the history saved by earlier sessions
+    fn load_history(&mut self) {
+        let mut string = String::new();
+        if let Some(mut file) = File::open(HISTORY_FILE) {
+            file.read_to_string(&mut string);
+        }
+        for line in string.lines() {
+            if !line.is_empty() && self.history.last().map_or(true, |last| last != line) {
+                self.history.push(line.to_string());
+            }
+        }
+    }
+
+    /// Add a command to the history, unless it repeats the last one, and save it
+    fn add_history(&mut self, command: &str) {
+        if command.is_empty() || self.history.last().map_or(false, |last| last == command) {
             return;
         }
+        self.history.push(command.to_string());
 
-        if value.is_empty() {
-            let mut remove = -1;
-            for i in 0..self.variables.len() {
-                match self.variables.get(i) {
-                    Some(variable) => if variable.name == name {
-                        remove = i as isize;
-                        break;
-                    },
-                    None => break,
+        match open_output(HISTORY_FILE, true) {
+            Some(mut file) => if file.write(&(command.to_string() + "\n").as_bytes()).is_none() {
+                println!("Failed to write: {}", HISTORY_FILE);
+            },
+            None => println!("Failed to open file: {}", HISTORY_FILE),
+        }
+    }
+
+    /// Replace `!!` with the last command and `!n` with the command at history index `n`
+    /// A `!` in single quotes or after a backslash is kept, quotes are tracked as in
+    /// `unterminated_quote`
+    fn expand_history(&self, command: &str) -> Result<String, String> {
+        let mut expanded = String::new();
+
+        let mut quote = None;
+        let mut chars = command.chars().peekable();
+        while let Some(c) = chars.next() {
+            let escaped = match quote {
+                Some(open) => if c == open {
+                    quote = None;
+                    false
+                } else {
+                    c == '\\' && open == '"'
+                },
+                None => if c == '\'' || c == '"' {
+                    quote = Some(c);
+                    false
+                } else {
+                    c == '\\'
+                },
+            };
+            if escaped {
+                expanded.push(c);
+                if let Some(next) = chars.next() {
+                    expanded.push(next);
                 }
+                continue;
             }
 
-            if remove >= 0 {
-                self.variables.remove(remove as usize);
+            if c != '!' || quote == Some('\'') {
+                expanded.push(c);
+                continue;
             }
-        } else {
-            for variable in self.variables.iter_mut() {
-                if variable.name == name {
-                    variable.value = value.to_string();
-                    return;
+
+            if chars.peek() == Some(&'!') {
+                chars.next();
+                match self.history.last() {
+                    Some(last) => expanded = expanded + last,
+                    None => return Err("No commands in history".to_string()),
+                }
+            } else if chars.peek().map_or(false, |c| c.is_digit(10)) {
+                let mut index = String::new();
+                while let Some(&digit) = chars.peek() {
+                    if !digit.is_digit(10) {
+                        break;
+                    }
+                    index.push(digit);
+                    chars.next();
                 }
+                match self.history.get(index.to_num()) {
+                    Some(entry) => expanded = expanded + entry,
+                    None => return Err(format!("No command at history index {}", index)),
+                }
+            } else {
+                expanded.push(c);
             }
+        }
 
-            self.variables.push(Variable {
-                name: name.to_string(),
-                value: value.to_string(),
-            });
+        Ok(expanded)
+    }
+
+    /// Register an additional command, before running the application
+    pub fn register(&mut self, command: Command<'a>) {
+        self.commands.register(command);
+    }
+
+    /// Print the variables, except `?`, as assignments that set them again
+    fn print_variables(&self) {
+        for (name, value) in self.variables.iter() {
+            if name != "?" {
+                println!("{}={}", name, quote(value));
+            }
+        }
+    }
+
+    /// Print the aliases as `alias` commands that define them again
+    fn print_aliases(&self) {
+        for (name, words) in self.aliases.iter() {
+            println!("alias {} {}", quote(name), quote(words));
+        }
+    }
+
+    /// Get the value of a variable
+    pub fn get_var(&self, name: &str) -> Option<String> {
+        self.variables.get(name).map(|value| value.clone())
+    }
+
+    /// Set the indexed variables `name_0`, `name_1`, ... to `values`, and `name_count` to
+    /// how many there are
+    pub fn set_array(&mut self, name: &str, values: &[String]) {
+        for (i, value) in values.iter().take(ARRAY_MAX).enumerate() {
+            self.set_var(&format!("{}_{}", name, i), value);
+        }
+        self.set_var(&format!("{}_count", name), &format!("{}", cmp::min(values.len(), ARRAY_MAX)));
+    }
+
+    /// Set a variable, or remove it if the value is empty
+    pub fn set_var(&mut self, name: &str, value: &str) {
+        if name.is_empty() {
+            return;
+        }
+
+        if value.is_empty() {
+            self.variables.remove(name);
+        } else {
+            self.variables.insert(name.to_string(), value.to_string());
         }
     }
 
@@ -546,30 +3969,87 @@ impl<'a> Application<'a> {
 
     /// Run the application
     pub fn main(&mut self) {
+        if args().get(1) == Some(&"--check") {
+            let path = args().get(2).map_or("", |path| *path);
+            let mut script = String::new();
+            if let Some(mut file) = File::open(path) {
+                file.read_to_string(&mut script);
+            } else {
+                println!("Failed to open file: {}", path);
+                unsafe { sys_exit(1) };
+            }
+
+            let errors = check_syntax(&script);
+            for &(line, ref error) in errors.iter() {
+                println!("{}:{}: {}", path, line, error);
+            }
+            unsafe { sys_exit(if errors.is_empty() { 0 } else { 1 }) };
+        }
+
+        //A program that exited in raw mode may have left the console raw
+        print_flush(COOKED_MODE);
+
+        self.load_history();
+
         println!("Type help for a command list");
-        if let Some(arg) = args().get(1) {
-            let command = "run ".to_string() + arg;
+        if args().len() > 1 {
+            let mut command = "run".to_string();
+            for arg in args().iter().skip(1) {
+                command = command + " " + arg;
+            }
             println!("user@redox:{}# {}", self.get_current_directory(), command);
             self.on_command(&command);
         }
 
         loop {
+            self.console_size = query_console_size();
+
+            let mut prompt = String::new();
             for mode in self.modes.iter().rev() {
                 if mode.value {
-                    print!("+ ");
+                    prompt.push_str("+ ");
                 } else {
-                    print!("- ");
+                    prompt.push_str("- ");
                 }
             }
-            print!("user@redox:{}# ", self.get_current_directory());
-            if let Some(command_original) = readln!() {
+            if self.reading_loop.is_some() {
+                prompt.push_str("> ");
+            } else {
+                let ps1 = self.get_var("PS1").unwrap_or(DEFAULT_PROMPT.to_string());
+                prompt = prompt + &render_prompt(&ps1, &self.get_current_directory());
+            }
+            print_flush(&prompt);
+            if let Some(mut command_original) = readln!() {
+                while continues(command_original.trim_right()) {
+                    command_original = command_original.trim_right().to_string();
+                    command_original.pop();
+                    print_flush("> ");
+                    match readln!() {
+                        Some(line) => command_original.push_str(&line),
+                        None => break,
+                    }
+                }
+
                 let command = command_original.trim();
-                if command == "exit" {
+                if command.contains('\x03') {
+                    self.interrupted.set(true);
+                    self.reading_loop = None;
+                } else if command == "exit" {
                     println!("Exit temporarily blocked (due to using terminal as init)")
                     //break;
                 } else if !command.is_empty() {
-                    self.on_command(&command);
+                    match self.expand_history(&command) {
+                        Ok(expanded) => {
+                            if expanded != command {
+                                println!("{}", expanded);
+                            }
+                            self.add_history(&expanded);
+                            self.on_command(&expanded);
+                        }
+                        Err(error) => println!("{}", error),
+                    }
                 }
+                self.interrupted.set(false);
             } else {
                 println!("Failed to read from stdin");
             }
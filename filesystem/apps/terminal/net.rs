@@ -0,0 +1,407 @@
+use redox::io::Read;
+use redox::string::{String, ToString};
+use redox::time::{Clock, Duration};
+use redox::to_num::ToNum;
+use redox::vec::Vec;
+
+/// A network URL, split into its parts
+#[derive(Clone)]
+pub struct NetUrl {
+    /// The scheme, like `http`
+    pub scheme: String,
+    /// The host name or address
+    pub host: String,
+    /// The port, the default port of the scheme if none was given
+    pub port: usize,
+    /// The path, starting with a slash
+    pub path: String,
+    /// The query, without the question mark
+    pub query: String,
+}
+
+impl NetUrl {
+    /// Parse a URL like `scheme://host:port/path?query`
+    /// Only `http`, `tcp` and `udp` URLs are supported, and `tcp` and `udp` need a port
+    pub fn parse(url: &str) -> Result<NetUrl, String> {
+        let (scheme, rest) = match url.find("://") {
+            Some(i) => (&url[.. i], &url[i + 3 ..]),
+            None => return Err(format!("No scheme in {}", url)),
+        };
+
+        let default_port = match scheme {
+            "http" => Some(80),
+            "tcp" | "udp" => None,
+            _ => return Err(format!("Unsupported scheme: {}", scheme)),
+        };
+
+        let (authority, target) = match rest.find('/') {
+            Some(i) => (&rest[.. i], &rest[i ..]),
+            None => (rest, "/"),
+        };
+
+        let (host, port) = match authority.rfind(':') {
+            Some(i) => {
+                let port = &authority[i + 1 ..];
+                if port.is_empty() || !port.chars().all(|c| c.is_digit(10)) {
+                    return Err(format!("Invalid port: {}", port));
+                }
+                (&authority[.. i], port.to_num())
+            }
+            None => match default_port {
+                Some(port) => (authority, port),
+                None => return Err(format!("No port given for {}", scheme)),
+            },
+        };
+
+        if host.is_empty() {
+            return Err(format!("No host in {}", url));
+        }
+
+        let (path, query) = match target.find('?') {
+            Some(i) => (&target[.. i], &target[i + 1 ..]),
+            None => (target, ""),
+        };
+
+        Ok(NetUrl {
+            scheme: scheme.to_string(),
+            host: host.to_string(),
+            port: port,
+            path: path.to_string(),
+            query: query.to_string(),
+        })
+    }
+
+    /// Resolve a `Location` header against this URL
+    /// It can be a whole URL, a path from the root, or a path relative to this one
+    pub fn join(&self, location: &str) -> Result<NetUrl, String> {
+        if location.contains("://") {
+            return NetUrl::parse(location);
+        }
+        if location.starts_with("//") {
+            return NetUrl::parse(&format!("{}:{}", self.scheme, location));
+        }
+
+        let target = if location.starts_with('/') {
+            location.to_string()
+        } else {
+            let dir = match self.path.rfind('/') {
+                Some(i) => &self.path[.. i + 1],
+                None => "/",
+            };
+            dir.to_string() + location
+        };
+        NetUrl::parse(&format!("{}://{}:{}{}", self.scheme, self.host, self.port, target))
+    }
+
+    /// The path and query, as used in a HTTP request line
+    pub fn target(&self) -> String {
+        if self.query.is_empty() {
+            self.path.clone()
+        } else {
+            self.path.clone() + "?" + &self.query
+        }
+    }
+
+    /// The URL of the socket to connect to, `http` going over `tcp`
+    pub fn socket(&self) -> String {
+        let transport = if self.scheme == "udp" {
+            "udp"
+        } else {
+            "tcp"
+        };
+        format!("{}://{}:{}", transport, self.host, self.port)
+    }
+
+    /// The last part of the path, or `index.html` if the path ends with a slash
+    pub fn file_name(&self) -> String {
+        match self.path.rsplit('/').next() {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => "index.html".to_string(),
+        }
+    }
+}
+
+/// A HTTP response, split into its status, headers and body
+pub struct HttpResponse {
+    /// The status code, like 200
+    pub status: usize,
+    /// The reason phrase after the status code, like `OK`
+    pub reason: String,
+    /// The headers, in the order they were sent
+    pub headers: Vec<(String, String)>,
+    /// The body, without any chunked transfer encoding
+    pub body: Vec<u8>,
+}
+
+/// Are the two strings equal, ignoring the case of ASCII letters?
+fn eq_ignore_case(a: &str, b: &str) -> bool {
+    fn lower(c: u8) -> u8 {
+        if c >= b'A' && c <= b'Z' {
+            c - b'A' + b'a'
+        } else {
+            c
+        }
+    }
+    a.len() == b.len() && a.bytes().zip(b.bytes()).all(|(a, b)| lower(a) == lower(b))
+}
+
+/// The position just after the first `\r\n\r\n` in `data`
+fn header_end(data: &[u8]) -> Option<usize> {
+    data.windows(4).position(|window| window == b"\r\n\r\n").map(|i| i + 4)
+}
+
+/// Decode a chunked body
+fn dechunk(mut data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = match data.windows(2).position(|window| window == b"\r\n") {
+            Some(i) => i,
+            None => return Err("Chunk size without an end".to_string()),
+        };
+        let line = String::from_utf8_lossy(&data[.. line_end]).into_owned();
+        //Chunk extensions follow a ;
+        let size_hex = line.split(';').next().unwrap_or("").trim().to_string();
+        if size_hex.is_empty() || !size_hex.chars().all(|c| c.is_digit(16)) {
+            return Err(format!("Invalid chunk size: {}", line));
+        }
+        let size = size_hex.to_num_radix(16);
+        data = &data[line_end + 2 ..];
+
+        if size == 0 {
+            return Ok(body);
+        }
+        if data.len() < size {
+            return Err("Chunk shorter than its size".to_string());
+        }
+        body.extend(data[.. size].iter().cloned());
+        data = &data[size ..];
+        if data.starts_with(b"\r\n") {
+            data = &data[2 ..];
+        }
+    }
+}
+
+impl HttpResponse {
+    /// Parse a whole response, as read from a connection that the server closed
+    /// The body is cut to `Content-Length`, or decoded if its transfer encoding is chunked
+    pub fn parse(data: &[u8]) -> Result<HttpResponse, String> {
+        let end = match header_end(data) {
+            Some(end) => end,
+            None => return Err("Response without the end of its headers".to_string()),
+        };
+        let head = String::from_utf8_lossy(&data[.. end - 4]).into_owned();
+        let mut lines = head.split("\r\n");
+
+        let status_line = lines.next().unwrap_or("");
+        let mut parts = status_line.splitn(3, ' ');
+        let version = parts.next().unwrap_or("");
+        let status = parts.next().unwrap_or("");
+        let reason = parts.next().unwrap_or("");
+        if !version.starts_with("HTTP/") || status.len() != 3 ||
+           !status.chars().all(|c| c.is_digit(10)) {
+            return Err(format!("Invalid status line: {}", status_line));
+        }
+
+        let mut headers = Vec::new();
+        for line in lines {
+            match line.find(':') {
+                Some(i) => headers.push((line[.. i].trim().to_string(), line[i + 1 ..].trim().to_string())),
+                None => return Err(format!("Invalid header: {}", line)),
+            }
+        }
+
+        let mut response = HttpResponse {
+            status: status.to_num(),
+            reason: reason.to_string(),
+            headers: headers,
+            body: Vec::new(),
+        };
+
+        let rest = &data[end ..];
+        let chunked = response.header("Transfer-Encoding")
+                              .map_or(false, |encoding| eq_ignore_case(encoding, "chunked"));
+        response.body = if chunked {
+            try!(dechunk(rest))
+        } else {
+            match response.header("Content-Length") {
+                Some(length) => {
+                    if length.is_empty() || !length.chars().all(|c| c.is_digit(10)) {
+                        return Err(format!("Invalid Content-Length: {}", length));
+                    }
+                    let length = length.to_num();
+                    if rest.len() < length {
+                        return Err(format!("Body is {} bytes, shorter than its Content-Length of {}",
+                                           rest.len(),
+                                           length));
+                    }
+                    rest[.. length].to_vec()
+                }
+                None => rest.to_vec(),
+            }
+        };
+
+        Ok(response)
+    }
+
+    /// The value of a header, whose name is matched ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|&&(ref header, _)| eq_ignore_case(header, name))
+            .map(|&(_, ref value)| &value[..])
+    }
+}
+
+/// Read to the end, giving up once `timeout` has passed on `clock`
+/// Returns `Some(false)` on a timeout, and `None` if reading failed
+///
+/// The deadline is checked between reads, so a read that never returns still blocks
+pub fn read_timeout<R: Read>(reader: &mut R, vec: &mut Vec<u8>, timeout: Duration,
+                             clock: &Clock) -> Option<bool> {
+    let deadline = clock.monotonic_ms() + timeout.to_millis();
+    let mut bytes = [0; 4096];
+    loop {
+        if clock.monotonic_ms() > deadline {
+            return Some(false);
+        }
+
+        match reader.read(&mut bytes) {
+            Some(0) => return Some(true),
+            Some(count) => vec.extend(bytes[.. count].iter().cloned()),
+            None => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use redox::io::Read;
+    use redox::time::{Duration, FakeClock};
+    use redox::vec::Vec;
+
+    /// Gives a byte on every read until it runs out, with every read taking `ms` on the clock
+    struct SlowReader<'a> {
+        clock: &'a FakeClock,
+        ms: u64,
+        left: usize,
+    }
+
+    impl<'a> Read for SlowReader<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+            self.clock.advance(self.ms);
+            if self.left == 0 {
+                return Some(0);
+            }
+            self.left -= 1;
+            buf[0] = b'x';
+            Some(1)
+        }
+    }
+
+    #[test]
+    fn http_default_port() {
+        let url = NetUrl::parse("http://example.com").ok().unwrap();
+        assert_eq!(url.scheme, "http");
+        assert_eq!(url.host, "example.com");
+        assert_eq!(url.port, 80);
+        assert_eq!(url.path, "/");
+        assert_eq!(url.query, "");
+        assert_eq!(url.socket(), "tcp://example.com:80");
+        assert_eq!(url.file_name(), "index.html");
+    }
+
+    #[test]
+    fn port_path_query() {
+        let url = NetUrl::parse("http://10.0.2.2:8080/files/a.txt?x=1&y=2").ok().unwrap();
+        assert_eq!(url.host, "10.0.2.2");
+        assert_eq!(url.port, 8080);
+        assert_eq!(url.path, "/files/a.txt");
+        assert_eq!(url.query, "x=1&y=2");
+        assert_eq!(url.target(), "/files/a.txt?x=1&y=2");
+        assert_eq!(url.socket(), "tcp://10.0.2.2:8080");
+        assert_eq!(url.file_name(), "a.txt");
+    }
+
+    #[test]
+    fn raw_sockets() {
+        let url = NetUrl::parse("udp://10.0.2.2:53").ok().unwrap();
+        assert_eq!(url.socket(), "udp://10.0.2.2:53");
+        assert_eq!(url.target(), "/");
+
+        assert!(NetUrl::parse("tcp://10.0.2.2").is_err());
+    }
+
+    #[test]
+    fn join() {
+        let url = NetUrl::parse("http://example.com/files/a.txt").ok().unwrap();
+
+        let other = url.join("http://other.org:8080/b?c=d").ok().unwrap();
+        assert_eq!(other.host, "other.org");
+        assert_eq!(other.port, 8080);
+        assert_eq!(other.target(), "/b?c=d");
+
+        let protocol_relative = url.join("//cdn.example.com/x").ok().unwrap();
+        assert_eq!(protocol_relative.socket(), "tcp://cdn.example.com:80");
+
+        let rooted = url.join("/moved/a.txt").ok().unwrap();
+        assert_eq!(rooted.socket(), "tcp://example.com:80");
+        assert_eq!(rooted.path, "/moved/a.txt");
+
+        let relative = url.join("b.txt?v=2").ok().unwrap();
+        assert_eq!(relative.target(), "/files/b.txt?v=2");
+
+        assert!(url.join("ftp://example.com/").is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(NetUrl::parse("example.com").is_err());
+        assert!(NetUrl::parse("ftp://example.com/").is_err());
+        assert!(NetUrl::parse("http://:80/").is_err());
+        assert!(NetUrl::parse("http://example.com:/").is_err());
+        assert!(NetUrl::parse("http://example.com:x/").is_err());
+    }
+
+    #[test]
+    fn http_response() {
+        let response = HttpResponse::parse(b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\ncontent-length: 5\r\n\r\nhello, and more").ok().unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.reason, "OK");
+        assert_eq!(response.header("Content-Type"), Some("text/plain"));
+        assert_eq!(response.body, b"hello");
+
+        let not_found = HttpResponse::parse(b"HTTP/1.0 404 Not Found\r\n\r\nmissing").ok().unwrap();
+        assert_eq!(not_found.status, 404);
+        assert_eq!(not_found.reason, "Not Found");
+        assert_eq!(not_found.body, b"missing");
+    }
+
+    #[test]
+    fn chunked_response() {
+        let response = HttpResponse::parse(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\na;x=y\r\n, chunked!\r\n0\r\n\r\n").ok().unwrap();
+        assert_eq!(response.body, b"hello, chunked!");
+    }
+
+    #[test]
+    fn invalid_response() {
+        assert!(HttpResponse::parse(b"HTTP/1.1 200 OK\r\n").is_err());
+        assert!(HttpResponse::parse(b"SSH-2.0\r\n\r\n").is_err());
+        assert!(HttpResponse::parse(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort").is_err());
+        assert!(HttpResponse::parse(b"HTTP/1.1 200 OK\r\nno colon\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn read_timeout_deadline() {
+        let clock = FakeClock::new();
+        let mut data = Vec::new();
+        let mut fast = SlowReader { clock: &clock, ms: 100, left: 5 };
+        assert_eq!(read_timeout(&mut fast, &mut data, Duration::new(1, 0), &clock), Some(true));
+        assert_eq!(data.len(), 5);
+
+        let mut data = Vec::new();
+        let mut slow = SlowReader { clock: &clock, ms: 400, left: 10 };
+        assert_eq!(read_timeout(&mut slow, &mut data, Duration::new(1, 0), &clock), Some(false));
+        assert_eq!(data.len(), 3);
+    }
+}
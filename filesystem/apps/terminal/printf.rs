@@ -0,0 +1,190 @@
+use redox::string::{String, ToString};
+use redox::vec::Vec;
+
+use super::arith;
+
+/// Format arguments with `printf` specifiers: `%s` text, `%d` decimal, `%x` hexadecimal,
+/// `%c` the first character and `%%` a percent sign
+/// A specifier can have the flags `-` to pad on the right and `0` to pad with zeros, a
+/// width, and a precision, the most characters of text or the fewest digits of a number
+/// The format is repeated while arguments are left, and missing ones are empty, or 0
+pub fn format(format: &str, args: &[String]) -> Result<String, String> {
+    let mut output = String::new();
+    let mut next = 0;
+    loop {
+        let used = try!(format_once(format, &args[next ..], &mut output));
+        next += used;
+        if used == 0 || next >= args.len() {
+            return Ok(output);
+        }
+    }
+}
+
+/// A parsed specifier, such as `%-08.3d`
+struct Spec {
+    left: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    conversion: char,
+}
+
+/// Take the digits at the start of the characters, as a number
+fn take_number(chars: &mut Vec<char>) -> Option<usize> {
+    let mut number = None;
+    while let Some(&c) = chars.last() {
+        if c < '0' || c > '9' {
+            break;
+        }
+        number = Some(number.unwrap_or(0) * 10 + (c as usize - '0' as usize));
+        chars.pop();
+    }
+    number
+}
+
+/// Format once, returning how many arguments were used
+fn format_once(format: &str, args: &[String], output: &mut String) -> Result<usize, String> {
+    //Reversed, so characters are taken from the end
+    let mut chars: Vec<char> = format.chars().rev().collect();
+    let mut used = 0;
+    while let Some(c) = chars.pop() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+
+        let mut spec = Spec {
+            left: false,
+            zero: false,
+            width: 0,
+            precision: None,
+            conversion: '%',
+        };
+        while let Some(&flag) = chars.last() {
+            match flag {
+                '-' => spec.left = true,
+                '0' => spec.zero = true,
+                _ => break,
+            }
+            chars.pop();
+        }
+        spec.width = take_number(&mut chars).unwrap_or(0);
+        if chars.last() == Some(&'.') {
+            chars.pop();
+            spec.precision = Some(take_number(&mut chars).unwrap_or(0));
+        }
+        spec.conversion = match chars.pop() {
+            Some(conversion) => conversion,
+            None => return Err("Format ends with a lone %".to_string()),
+        };
+
+        if spec.conversion == '%' {
+            output.push('%');
+            continue;
+        }
+
+        let arg = match args.get(used) {
+            Some(arg) => &arg[..],
+            None => "",
+        };
+        used += 1;
+
+        let text = match spec.conversion {
+            's' => match spec.precision {
+                Some(precision) => arg.chars().take(precision).collect(),
+                None => arg.to_string(),
+            },
+            'c' => arg.chars().next().map_or(String::new(), |c| c.to_string()),
+            'd' | 'x' => {
+                let number = if arg.is_empty() {
+                    0
+                } else {
+                    try!(arith::number(arg))
+                };
+                let digits = if spec.conversion == 'd' {
+                    format!("{}", number).trim_left_matches('-').to_string()
+                } else {
+                    format!("{:x}", number)
+                };
+                let mut text = String::new();
+                if spec.conversion == 'd' && number < 0 {
+                    text.push('-');
+                }
+                for _ in digits.len() .. spec.precision.unwrap_or(0) {
+                    text.push('0');
+                }
+                text + &digits
+            }
+            other => return Err(format!("Unknown format specifier: %{}", other)),
+        };
+
+        let len = text.chars().count();
+        if len >= spec.width {
+            output.push_str(&text);
+        } else if spec.left {
+            output.push_str(&text);
+            for _ in len .. spec.width {
+                output.push(' ');
+            }
+        } else if spec.zero && spec.precision.is_none() && spec.conversion != 's' && spec.conversion != 'c' {
+            //Zeros go after the sign
+            let (sign, digits) = if text.starts_with('-') {
+                ("-", &text[1 ..])
+            } else {
+                ("", &text[..])
+            };
+            output.push_str(sign);
+            for _ in len .. spec.width {
+                output.push('0');
+            }
+            output.push_str(digits);
+        } else {
+            for _ in len .. spec.width {
+                output.push(' ');
+            }
+            output.push_str(&text);
+        }
+    }
+    Ok(used)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use redox::string::{String, ToString};
+    use redox::vec::Vec;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn specifiers() {
+        assert_eq!(format("%s=%d", &strings(&["x", "42"])).ok().unwrap(), "x=42");
+        assert_eq!(format("%x", &strings(&["255"])).ok().unwrap(), "ff");
+        assert_eq!(format("%c", &strings(&["hello"])).ok().unwrap(), "h");
+        assert_eq!(format("100%%", &[]).ok().unwrap(), "100%");
+        assert!(format("%q", &strings(&["x"])).is_err());
+        assert!(format("%", &[]).is_err());
+        assert!(format("%d", &strings(&["x"])).is_err());
+    }
+
+    #[test]
+    fn width_precision() {
+        assert_eq!(format("[%5s]", &strings(&["ab"])).ok().unwrap(), "[   ab]");
+        assert_eq!(format("[%-5s]", &strings(&["ab"])).ok().unwrap(), "[ab   ]");
+        assert_eq!(format("[%.2s]", &strings(&["abcd"])).ok().unwrap(), "[ab]");
+        assert_eq!(format("[%05d]", &strings(&["-42"])).ok().unwrap(), "[-0042]");
+        assert_eq!(format("[%.3d]", &strings(&["7"])).ok().unwrap(), "[007]");
+        assert_eq!(format("[%6.3d]", &strings(&["-7"])).ok().unwrap(), "[  -007]");
+        assert_eq!(format("[%04x]", &strings(&["10"])).ok().unwrap(), "[000a]");
+    }
+
+    #[test]
+    fn arguments() {
+        assert_eq!(format("%s,", &strings(&["a", "b", "c"])).ok().unwrap(), "a,b,c,");
+        assert_eq!(format("%s %s;", &strings(&["a", "b", "c"])).ok().unwrap(), "a b;c ;");
+        assert_eq!(format("[%s|%d]", &[]).ok().unwrap(), "[|0]");
+        assert_eq!(format("plain", &strings(&["unused"])).ok().unwrap(), "plain");
+    }
+}
@@ -0,0 +1,3 @@
+# Run the shell's own tests, stopping each script at its first failed assert
+# ? is 0 if all of them passed
+run -e tests
@@ -0,0 +1,12 @@
+# Aliases replace the first word of a command, but not the words they are replaced by
+alias greet "echo hello"
+word=$(greet)
+assert $word == hello
+
+alias echo "echo again"
+words=$(echo once)
+assert "$words" == "again once"
+unalias echo greet
+
+word=$(echo once)
+assert $word == once
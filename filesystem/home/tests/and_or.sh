@@ -0,0 +1,19 @@
+# && runs the next command only after a success, and || only after a failure
+# The failing cds print an error
+result=none
+assert 1 == 1 && result=and
+assert $result == and
+
+result=none
+cd /missing/directory/ && result=and
+assert $result == none
+
+cd /missing/directory/ || result=or
+assert $result == or
+
+result=none
+assert 1 == 1 || result=or
+assert $result == none
+
+cd /missing/directory/ && result=and || result=fallback
+assert $result == fallback
@@ -0,0 +1,10 @@
+# Arguments after the script are set in 1, 2, ... while it runs
+run tests/lib/args.sh one two
+assert $first == one
+assert $second == two
+assert x$1 == x
+
+source tests/lib/args.sh three
+assert $first == three
+first=
+second=
@@ -0,0 +1,12 @@
+# $((expression)) is replaced by its value
+x=$((1 + 2 * 3))
+assert $x == 7
+
+y=$(((x - 1) / 4 % 2))
+assert $y == 1
+
+assert $((-x)) == -7
+assert $(($x * 2))$((y)) == 141
+
+z=$((1 / 0))
+assert $? == 2
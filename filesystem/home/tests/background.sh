@@ -0,0 +1,12 @@
+# Only programs run with exec can be put in the background
+echo running &
+assert $? == 1
+
+# With no programs in the background, jobs lists nothing and wait returns at once
+jobs | mapfile listed
+assert $listed_count == 0
+wait
+assert $? == 0
+
+echo "a & b" | mapfile quoted
+assert "$quoted_0" == "a & b"
@@ -0,0 +1,11 @@
+# cat prints files one after another, numbering lines on across them
+cat tests/lib/size.txt tests/lib/size.txt | mapfile lines
+assert $lines_count == 2
+assert $lines_1 == 12345
+
+cat -n tests/lib/size.txt tests/lib/size.txt | mapfile lines
+assert "$lines_1" == "     2	12345"
+
+cat missing.txt tests/lib/size.txt | mapfile lines
+assert $lines_count == 2
+assert $lines_1 == 12345
@@ -0,0 +1,11 @@
+# cd goes home without a directory, and back with -
+start=$(pwd)
+HOME=$start
+cd tests
+cd
+assert $(pwd) == $start
+
+cd tests
+cd - | mapfile back
+assert $back_0 == $start
+HOME=
@@ -0,0 +1,12 @@
+# cp -r refuses to copy a directory into itself, however the paths are written
+cp -r tests ./tests/copy
+assert $? == 1
+cp -r ./tests tests/../tests/copy
+assert $? == 1
+if -e tests/copy
+  result=copied
+else
+  result=refused
+fi
+assert $result == refused
+result=
@@ -0,0 +1,23 @@
+# declare prints the variables and aliases so that sourcing its output sets them again
+tricky="it's \"quoted\", \$dollar; semi | pipe > arrow"
+plain=/home/file.txt
+alias greet "echo 'hello there'"
+declare > declared.sh
+
+tricky=
+plain=
+unalias greet
+source declared.sh
+assert "$tricky" == "it's \"quoted\", \$dollar; semi | pipe > arrow"
+assert $plain == /home/file.txt
+words=$(greet)
+assert "$words" == "hello there"
+
+declare | grep "?=" | mapfile found
+assert $found_count == 0
+
+rm declared.sh
+unalias greet
+tricky=
+plain=
+words=
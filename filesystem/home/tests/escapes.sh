@@ -0,0 +1,23 @@
+# Backslashes escape spaces, dollar signs, quotes and operators, and continue lines
+spaced=foo\ bar
+assert "$spaced" == "foo bar"
+
+name=redox
+dollar=\$name
+assert $dollar == '$name'
+assert "\$name" == '$name'
+
+quote=\"
+assert $quote == '"'
+assert "say \"hi\"" == 'say "hi"'
+
+semicolon=a\;b
+assert $semicolon == 'a;b'
+
+joined=first\
+second
+assert $joined == firstsecond
+
+# \xNN in echo -e is a Latin-1 character
+echo -e 'caf\xe9' | mapfile latin
+assert $latin_0 == café
@@ -0,0 +1,13 @@
+# Variables are replaced anywhere in a word, with braces to end the name
+dir=home
+assert path=/$dir/ == path=/home/
+assert ${dir}s == homes
+assert "${dir}_1" == home_1
+assert x${missing}y == xy
+
+# An unset variable on its own leaves no word
+count=0
+for word in $missing
+  count=1
+done
+assert $count == 0
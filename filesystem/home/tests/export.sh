@@ -0,0 +1,13 @@
+# Exported variables are listed by env, local ones are not
+local=1
+export shared=2
+env | mapfile listed
+assert $listed_count == 1
+assert $listed_0 == shared=2
+
+export local
+env | mapfile listed
+assert $listed_count == 2
+assert $listed_0 == local=1
+local=
+shared=
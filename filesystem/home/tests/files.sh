@@ -0,0 +1,30 @@
+# File tests, relative to the home directory the tests are run from
+result=none
+if -e run_tests.sh
+  result=exists
+fi
+assert $result == exists
+assert -f run_tests.sh
+assert -e tests
+
+if -e missing.txt
+  result=missing
+else
+  result=absent
+fi
+assert $result == absent
+
+if -d tests
+  result=dir
+fi
+assert $result == dir
+
+if -f tests
+  result=file
+fi
+assert $result == dir
+
+if -d run_tests.sh
+  result=file
+fi
+assert $result == dir
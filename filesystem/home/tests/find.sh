@@ -0,0 +1,11 @@
+# find searches directories for names, with wildcards
+find tests 'find.*' | mapfile found
+assert $found_count == 2
+assert $found_0 == tests/find.sh
+assert "$found_1" == "1 found"
+
+find tests/ define.sh | mapfile found
+assert $found_0 == tests/lib/define.sh
+
+find tests missing | mapfile found
+assert "$found_0" == "0 found"
@@ -0,0 +1,17 @@
+# for loops over words, variables holding lists, and an empty list
+last=none
+for word in a b c
+  last=$word
+done
+assert $last == c
+
+list=x y z
+for word in $list
+  last=$word
+done
+assert $last == z
+
+for word in
+  last=empty
+done
+assert $last == z
@@ -0,0 +1,4 @@
+# free -h prints the memory with units
+free -h | mapfile memory
+assert $memory_count == 2
+assert "$memory_0" == "           total      used      free"
@@ -0,0 +1,15 @@
+# grep filters the lines of its input by a substring
+echo -e "one\nTwo\nthree" | grep t | mapfile found
+assert $found_count == 1
+assert $found_0 == three
+
+echo -e "one\nTwo\nthree" | grep -i t | mapfile found
+assert $found_count == 2
+assert $found_0 == Two
+
+echo -e "one\nTwo\nthree" | grep -v o | mapfile found
+assert $found_count == 1
+assert $found_0 == three
+
+echo one | grep missing
+assert $? == 1
@@ -0,0 +1,17 @@
+# head and tail print the first and last lines
+echo -e "1\n2\n3\n4\n5" | head -n 2 | mapfile lines
+assert $lines_count == 2
+assert $lines_1 == 2
+
+echo -e "1\n2\n3\n4\n5" | tail -n 2 | mapfile lines
+assert $lines_count == 2
+assert $lines_0 == 4
+assert $lines_1 == 5
+
+echo -e "1\n2\n3" | tail | mapfile lines
+assert $lines_count == 3
+
+echo 1 | head -n -1
+assert $? == 1
+echo 1 | tail -n x
+assert $? == 1
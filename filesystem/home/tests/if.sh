@@ -0,0 +1,46 @@
+# if, elif, else and nested conditions
+result=none
+if a == a
+  result=then
+else
+  result=else
+fi
+assert $result == then
+
+if 3 > 20
+  result=then
+else
+  if -1 < 0
+    result=nested
+  fi
+fi
+assert $result == nested
+
+if 1 == 2
+  result=first
+elif 2 == 2
+  result=second
+elif 3 == 3
+  result=third
+else
+  result=else
+fi
+assert $result == second
+
+if 1 == 2
+  result=first
+elif 2 == 3
+  result=second
+else
+  result=else
+fi
+assert $result == else
+
+# A branch that is not taken does not empty the file it would redirect to
+echo kept > untaken.txt
+if 1 == 2
+  echo replaced > untaken.txt
+fi
+mapfile lines untaken.txt
+assert $lines_0 == kept
+rm untaken.txt
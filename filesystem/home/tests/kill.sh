@@ -0,0 +1,9 @@
+# kill refuses invalid PIDs and the kernel
+kill abc
+assert $? == 1
+
+kill 0
+assert $? == 1
+
+kill 99999
+assert $? == 1
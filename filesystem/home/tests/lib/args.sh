@@ -0,0 +1,3 @@
+# Run by arguments.sh, copies its arguments into variables
+first=$1
+second=$2
@@ -0,0 +1,5 @@
+# Sourced by source.sh, sets variables in the shell that sources it
+sourced=yes
+if $sourced == yes
+  branch=taken
+fi
@@ -0,0 +1,10 @@
+# ls -l lists types and sizes
+ls -l tests/lib | mapfile listed
+assert "$listed_0" == "Type       Size Name"
+assert $listed_count == 4
+
+ls -l tests/lib | grep size.txt | mapfile sized
+assert "$sized_0" == "file          5 size.txt"
+
+ls -l tests | grep lib/ | mapfile dirs
+assert "$dirs_0" == "dir           - lib/"
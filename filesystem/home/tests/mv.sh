@@ -0,0 +1,21 @@
+# mv refuses to move a file onto itself, however the path is written
+echo moving > moved.txt
+mv moved.txt ./moved.txt
+assert $? == 1
+mv moved.txt tests/../moved.txt
+assert $? == 1
+mv moved.txt .
+assert $? == 1
+assert -f moved.txt
+
+mv moved.txt renamed.txt
+assert $? == 0
+assert -f renamed.txt
+if -e moved.txt
+  result=kept
+else
+  result=moved
+fi
+assert $result == moved
+rm renamed.txt
+result=
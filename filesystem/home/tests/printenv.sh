@@ -0,0 +1,8 @@
+# printenv prints a single variable, and fails for one that is not set
+greeting=hello
+printenv greeting | mapfile printed
+assert $printed_0 == hello
+
+printenv missing
+assert $? == 1
+greeting=
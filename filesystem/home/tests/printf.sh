@@ -0,0 +1,9 @@
+# printf formats its arguments
+padded="$(printf "[%5s,%-3d,%03x]" ab 7 10)"
+assert "$padded" == "[   ab,7  ,00a]"
+
+repeated=$(printf "%s=%d," a 1 b)
+assert $repeated == a=1,b=0,
+
+printf "%d" word
+assert $? == 1
@@ -0,0 +1,6 @@
+# ps lists the processes in a table, or as JSON
+ps | mapfile listed
+assert "$listed_0" == "  PID STATE         MEM  FDS NAME"
+
+ps --json | mapfile listed
+assert "$listed_0" == "["
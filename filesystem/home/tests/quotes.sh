@@ -0,0 +1,22 @@
+# Quotes keep spaces in a word, single quotes keep $ as it is, double quotes replace variables
+name=redox
+quoted="hello world"
+assert "$quoted" == "hello world"
+
+single='$name'
+assert $single == '$name'
+
+double="name is $name"
+assert "$double" == "name is redox"
+
+empty=""
+assert "$empty" == ''
+
+result=none
+if "a b" == 'a b'; result=same; fi
+assert $result == same
+
+substituted='$(echo hi) $((1 + 1))'
+assert "$substituted" == '$(echo hi) $((1 + 1))'
+substituted="$(echo hi) $((1 + 1))"
+assert "$substituted" == "hi 2"
@@ -0,0 +1,12 @@
+# reset -v keeps exported variables
+export reset_kept=yes
+reset_dropped=yes
+reset -v
+assert $reset_kept == yes
+assert x$reset_dropped == x
+
+# reset ends a loop that is missing its done
+while 1 == 1
+  reset_dropped=never
+reset
+assert x$reset_dropped == x
@@ -0,0 +1,14 @@
+# rm checks its paths before removing anything
+rm -f missing.txt
+assert $? == 0
+rm missing.txt
+assert $? == 1
+
+rm tests
+assert $? == 1
+rm -rf .
+assert $? == 1
+rm -rf file:/
+assert $? == 1
+rm -x run_tests.sh
+assert $? == 1
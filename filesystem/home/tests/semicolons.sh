@@ -0,0 +1,11 @@
+# Several commands on a line, separated by ;
+a=1; b=2;
+assert $a == 1; assert $b == 2
+
+result=none
+if $a == 1; result=then; else; result=else; fi
+assert $result == then
+
+count=0
+while $count == 0; count=1; done
+assert $count == 1
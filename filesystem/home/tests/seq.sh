@@ -0,0 +1,22 @@
+# seq counts from a start to an end, by a step
+seq 3 | mapfile counted
+assert $counted_count == 3
+assert $counted_0 == 1
+assert $counted_2 == 3
+
+seq 2 2 9 | mapfile counted
+assert $counted_count == 4
+assert $counted_3 == 8
+
+seq 3 -1 1 | mapfile counted
+assert $counted_0 == 3
+assert $counted_2 == 1
+
+seq 5 1 | mapfile counted
+assert $counted_count == 0
+
+seq 1 0 5
+assert $? == 1
+
+seq x
+assert $? == 1
@@ -0,0 +1,12 @@
+# sleep takes fractional seconds and units
+sleep 10ms
+assert $? == 0
+
+sleep 0.01
+assert $? == 0
+
+sleep -1
+assert $? == 1
+
+sleep 1x
+assert $? == 1
@@ -0,0 +1,16 @@
+# sort orders lines as text, or as numbers with -n
+echo -e "pear\napple\nfig" | sort | mapfile sorted
+assert $sorted_count == 3
+assert $sorted_0 == apple
+assert $sorted_2 == pear
+
+echo -e "pear\napple\nfig" | sort -r | mapfile sorted
+assert $sorted_0 == pear
+
+echo -e "10\n9\n-2\n100" | sort -n | mapfile sorted
+assert $sorted_0 == -2
+assert $sorted_1 == 9
+assert $sorted_3 == 100
+
+echo -e "10\n9\n100" | sort -n -r | mapfile sorted
+assert $sorted_0 == 100
@@ -0,0 +1,13 @@
+# Scripts run with source or . set variables in this shell
+source tests/lib/define.sh
+assert $sourced == yes
+assert $branch == taken
+
+sourced=
+. tests/lib/define.sh
+assert $sourced == yes
+
+source tests/lib/missing.sh
+assert $? == 1
+sourced=
+branch=
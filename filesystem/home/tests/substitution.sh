@@ -0,0 +1,54 @@
+# Command substitution, nested and inside a larger argument
+word=$(echo hello)
+assert $word == hello
+
+nested=$(echo $(echo inner))
+assert $nested == inner
+
+joined=a$(echo b)c
+assert $joined == abc
+
+count=0
+while $(echo $count) != 1
+  count=1
+done
+assert $count == 1
+
+# Single quotes keep a substitution as it is
+literal='$(echo hello)'
+assert "$literal" == '$(echo hello)'
+
+# Output is never read as redirections, pipes or quotes
+arrow=$(echo 'a > substituted.txt')
+assert "$arrow" == "a > substituted.txt"
+test -e substituted.txt
+assert $? == 1
+
+piped=$(echo 'a | b')
+assert "$piped" == "a | b"
+
+apostrophe="$(echo "it's")"
+assert "$apostrophe" == "it's"
+
+# Unquoted output is split into words
+split=$(echo 'one   two')
+assert "$split" == "one two"
+
+# Substitutions in a branch that is not taken do not run, and neither do the conditions
+# of an if that is not reached or of an elif after a branch was taken
+ran=no
+if 1 == 2
+  echo $(ran=then)
+  if $(ran=nested) == x
+  fi
+elif 1 == 1
+  result=taken
+elif $(ran=elif) == x
+  result=elif
+else
+  echo $(ran=else)
+fi
+assert $ran == no
+assert $result == taken
+ran=
+result=
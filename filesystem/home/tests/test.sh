@@ -0,0 +1,37 @@
+# test and [ set ? from an expression
+x=5
+test $x -eq 5 && result=yes
+assert $result == yes
+
+test abc = abc
+assert $? == 0
+test abc != abc
+assert $? == 1
+test -z ""
+assert $? == 0
+test -n abc
+assert $? == 0
+
+[ 3 -lt 10 ]
+assert $? == 0
+[ 3 -gt 10 ]
+assert $? == 1
+[ -2 -le -2 ]
+assert $? == 0
+[ 7 -ge 8 ]
+assert $? == 1
+[ 1 -ne 2 ]
+assert $? == 0
+
+[ -f run_tests.sh ]
+assert $? == 0
+[ -d run_tests.sh ]
+assert $? == 1
+test -e missing.txt
+assert $? == 1
+
+[ 1 -eq 1
+assert $? == 2
+test abc -eq 1
+assert $? == 2
+result=
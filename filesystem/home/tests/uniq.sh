@@ -0,0 +1,13 @@
+# uniq removes adjacent duplicate lines, so it is used after sort
+echo -e "b\na\nb\na\na" | uniq | mapfile unique
+assert $unique_count == 4
+
+echo -e "b\na\nb\na\na" | sort | uniq | mapfile unique
+assert $unique_count == 2
+assert $unique_0 == a
+assert $unique_1 == b
+
+echo -e "b\na\nb\na\na" | sort | uniq -c | mapfile counted
+assert $counted_count == 2
+assert "$counted_0" == "      3 a"
+assert "$counted_1" == "      2 b"
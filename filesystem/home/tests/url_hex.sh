@@ -0,0 +1,4 @@
+# url_hex prints rows of 16 bytes, then the length
+url_hex -C file:/home/tests/lib/size.txt | mapfile dumped
+assert $dumped_count == 2
+assert "$dumped_1" == "00000005"
@@ -0,0 +1,8 @@
+# Setting, replacing and clearing variables
+name=redox
+assert $name == redox
+name=orbital
+assert $name != redox
+count=10
+assert $count >= 10
+assert $count < 11
@@ -0,0 +1,12 @@
+# wc counts lines, words and bytes
+lines=$(echo -e "one two\nthree" | wc -l)
+assert $lines == 2
+
+words=$(echo -e "one two\nthree" | wc -w)
+assert $words == 3
+
+bytes=$(echo -n abc | wc -c)
+assert $bytes == 3
+
+counts=$(echo a b | wc)
+assert "$counts" == "1 2 4"
@@ -0,0 +1,17 @@
+# A while loop counting up to 5, with a nested loop
+count=0
+inner=0
+while $count < 5
+  count=$(($count + 1))
+
+  while $inner == 0
+    inner=1
+  done
+done
+assert $count == 5
+assert $inner == 1
+
+while 1 == 2
+  count=never
+done
+assert $count == 5
@@ -1,16 +1,19 @@
 use redox::Box;
 use redox::cell::UnsafeCell;
-use redox::io::SeekFrom;
+use redox::fs::File;
+use redox::io::{SeekFrom, Write};
 use redox::rc::Rc;
 use redox::str;
-use redox::string::{String, ToString};
+use redox::string::String;
 
 use orbital::Color;
-use orbital::console::ConsoleWindow;
+use orbital::console::{ConsoleWindow, COOKED_MODE, POLL_INTERRUPT, RAW_MODE};
 
 pub struct Resource {
     console_window: Rc<UnsafeCell<Box<ConsoleWindow>>>,
     line_end_toggle: bool,
+    /// Did this resource switch the console to raw mode?
+    raw: bool,
 }
 
 impl Resource {
@@ -25,34 +28,45 @@ impl Resource {
     pub fn dup(&self) -> Option<Box<Self>> {
         Some(box Resource {
             console_window: self.console_window.clone(),
-            line_end_toggle: false
+            line_end_toggle: false,
+            raw: false,
         })
     }
 
     pub fn path(&self) -> Option<String> {
-        Some("console:".to_string() + &self.inner().window.title())
+        //console:///cols/rows/title
+        Some(format!("console:///{}/{}/{}",
+                     self.inner().cols(),
+                     self.inner().rows(),
+                     self.inner().window.title()))
     }
 
     pub fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
-        if self.line_end_toggle {
+        //Keys in raw mode are not lines, so they are not followed by an end of line
+        if self.line_end_toggle && !self.inner().raw {
             self.line_end_toggle = false;
             Some(0)
         } else {
             match self.inner_mut().read() {
                 Some(string) => {
-                    self.line_end_toggle = true;
+                    self.line_end_toggle = !self.inner().raw;
 
-                    let mut i = 0;
-
-                    for b in string.bytes() {
-                        if i < buf.len() {
-                            buf[i] = b;
-                            i += 1;
-                        } else {
+                    //Only copy whole characters, so no UTF-8 sequence is cut in half
+                    let mut end = string.len();
+                    for (index, c) in string.char_indices() {
+                        if index + c.len_utf8() > buf.len() {
+                            end = index;
                             break;
                         }
                     }
 
+                    let mut i = 0;
+
+                    for b in string.bytes().take(end) {
+                        buf[i] = b;
+                        i += 1;
+                    }
+
                     Some(i)
                 },
                 None => None
@@ -61,6 +75,24 @@ impl Resource {
     }
 
     pub fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        if buf == RAW_MODE.as_bytes() || buf == COOKED_MODE.as_bytes() {
+            self.raw = buf == RAW_MODE.as_bytes();
+            self.line_end_toggle = false;
+            self.inner_mut().raw = self.raw;
+            return Some(buf.len());
+        }
+
+        //Ctrl-C pressed while a command runs sets the interrupt flag of the kernel console,
+        //which the command checks
+        if buf == POLL_INTERRUPT.as_bytes() {
+            if self.inner_mut().poll_interrupt() {
+                if let Some(mut interrupt) = File::open("debug:interrupt") {
+                    interrupt.write(b"1");
+                }
+            }
+            return Some(buf.len());
+        }
+
         self.inner_mut().print(unsafe { &str::from_utf8_unchecked(buf) }, Color::rgba(224, 224, 224, 255));
         self.sync();
 
@@ -77,6 +109,16 @@ impl Resource {
     }
 }
 
+impl Drop for Resource {
+    /// Restore cooked mode if this resource left the console raw, so a program that exits
+    /// without restoring it does not leave the console unusable
+    fn drop(&mut self) {
+        if self.raw {
+            self.inner_mut().raw = false;
+        }
+    }
+}
+
 pub struct Scheme;
 
 impl Scheme {
@@ -93,7 +135,8 @@ impl Scheme {
 
         Some(box Resource {
             console_window: Rc::new(UnsafeCell::new(ConsoleWindow::new(-1, -1, 640, 480, title))),
-            line_end_toggle: false
+            line_end_toggle: false,
+            raw: false,
         })
     }
 }
@@ -2,6 +2,7 @@
 
 use redox::Box;
 use redox::{cmp, mem};
+use redox::cell::Cell;
 use redox::syscall::{sys_alloc, sys_unalloc};
 
 use orbital::Color;
@@ -50,7 +51,14 @@ pub struct VBEModeInfo {
 
 const VBEMODEINFO: *const VBEModeInfo = 0x5200 as *const VBEModeInfo;
 
+/// Viewport sizes that may be offered, if the framebuffer is large enough
+pub const MODES: [(usize, usize); 6] = [(1920, 1080), (1280, 1024), (1280, 720), (1024, 768), (800, 600), (640, 480)];
+
 /// A display
+///
+/// All drawing goes to `offscreen`, the back buffer, which is sized to the display
+/// (`bytesperrow * height` bytes, 3 MB at 1024x768x32). `flip` then copies the damaged
+/// rows forward to `onscreen` in one pass, so partial frames are never visible.
 pub struct Display {
     pub offscreen: usize,
     pub onscreen: usize,
@@ -59,6 +67,10 @@ pub struct Display {
     pub width: usize,
     pub height: usize,
     pub root: bool,
+    /// The first damaged row
+    damage_start: Cell<usize>,
+    /// The row after the last damaged row
+    damage_end: Cell<usize>,
 }
 
 impl Display {
@@ -74,7 +86,35 @@ impl Display {
             width: mode_info.xresolution as usize,
             height: mode_info.yresolution as usize,
             root: true,
+            damage_start: Cell::new(0),
+            damage_end: Cell::new(mode_info.yresolution as usize),
+        }
+    }
+
+    /// The resolution of the framebuffer set up by the bootloader
+    pub unsafe fn root_size() -> Size {
+        let mode_info = &*VBEMODEINFO;
+        Size::new(mode_info.xresolution as usize, mode_info.yresolution as usize)
+    }
+
+    /// Draw only to the top left `width` by `height` of the root framebuffer
+    /// This does not change the VBE mode set by the bootloader, the rest of the screen is cleared
+    /// The viewport must fit within the framebuffer of `root_size`
+    pub unsafe fn set_viewport(&mut self, width: usize, height: usize) -> bool {
+        let root_size = Display::root_size();
+        if !self.root || width == 0 || height == 0 || width > root_size.width || height > root_size.height {
+            return false;
         }
+
+        let reenable = scheduler::start_no_ints();
+        Display::set_run(0, self.onscreen, self.bytesperrow * root_size.height);
+        self.width = width;
+        self.height = height;
+        self.size = self.bytesperrow * height;
+        self.damage(0, height as isize);
+        scheduler::end_no_ints(reenable);
+
+        true
     }
 
     /// Create a new display
@@ -91,6 +131,8 @@ impl Display {
                 width: width,
                 height: height,
                 root: false,
+                damage_start: Cell::new(0),
+                damage_end: Cell::new(height),
             }
         }
     }
@@ -145,8 +187,58 @@ impl Display {
         }
     }
 
+    /// Mark the rows from `start_y` up to `end_y` as needing to be flipped
+    pub fn damage(&self, start_y: isize, end_y: isize) {
+        let start = cmp::max(0, cmp::min(self.height as isize, start_y)) as usize;
+        let end = cmp::max(0, cmp::min(self.height as isize, end_y)) as usize;
+        if start < end {
+            if self.damage_start.get() >= self.damage_end.get() {
+                self.damage_start.set(start);
+                self.damage_end.set(end);
+            } else {
+                self.damage_start.set(cmp::min(self.damage_start.get(), start));
+                self.damage_end.set(cmp::max(self.damage_end.get(), end));
+            }
+        }
+    }
+
+    /// Copy a run of pixels, swapping the red and blue bytes
+    /// This converts between RGBA and the native BGRA
+    pub unsafe fn copy_run_swap(src: usize, dst: usize, len: usize) {
+        let mut i = 0;
+        while len - i >= mem::size_of::<u32>() {
+            let pixel = *((src + i) as *const u32);
+            *((dst + i) as *mut u32) = (pixel & 0xFF00FF00) | ((pixel >> 16) & 0xFF) | ((pixel & 0xFF) << 16);
+            i += mem::size_of::<u32>();
+        }
+    }
+
+    /// Copy pixel data into the offscreen buffer at `offset`, swapping red and blue if `swap` is set
+    /// Only whole pixels that fit in the buffer are copied, and the number of bytes copied is returned
+    /// Returns `None`, without copying, if `offset` is outside of the buffer or not pixel aligned
+    pub fn write_offscreen(&self, offset: usize, data: &[u8], swap: bool) -> Option<usize> {
+        if offset > self.size || offset % mem::size_of::<u32>() != 0 {
+            return None;
+        }
+
+        let len = cmp::min(self.size - offset, data.len());
+        let len = len - len % mem::size_of::<u32>();
+        if len > 0 {
+            unsafe {
+                if swap {
+                    Display::copy_run_swap(data.as_ptr() as usize, self.offscreen + offset, len);
+                } else {
+                    Display::copy_run(data.as_ptr() as usize, self.offscreen + offset, len);
+                }
+            }
+        }
+
+        Some(len)
+    }
+
     /// Set the color
     pub fn set(&self, color: Color) {
+        self.damage(0, self.height as isize);
         unsafe {
             Display::set_run(color.data, self.offscreen, self.size);
         }
@@ -155,6 +247,7 @@ impl Display {
     /// Scroll the display
     pub fn scroll(&self, rows: usize) {
         if rows > 0 && rows < self.height {
+            self.damage(0, self.height as isize);
             let offset = rows * self.bytesperrow;
             unsafe {
                 Display::copy_run(self.offscreen + offset,
@@ -170,7 +263,16 @@ impl Display {
         unsafe {
             let reenable = scheduler::start_no_ints();
             if self.root {
-                Display::copy_run(self.offscreen, self.onscreen, self.size);
+                let start = self.damage_start.get();
+                let end = self.damage_end.get();
+                if start < end {
+                    let offset = start * self.bytesperrow;
+                    Display::copy_run(self.offscreen + offset,
+                                      self.onscreen + offset,
+                                      (end - start) * self.bytesperrow);
+                }
+                self.damage_start.set(0);
+                self.damage_end.set(0);
             } else {
                 let self_mut: *mut Self = mem::transmute(self);
                 mem::swap(&mut (*self_mut).offscreen,
@@ -189,6 +291,7 @@ impl Display {
             let start_y = cmp::max(0, cmp::min(self.height as isize - 1, point.y)) as usize;
             let end_y =
                 cmp::max(0, cmp::min(self.height as isize - 1, point.y + size.height as isize)) as usize;
+            self.damage(start_y as isize, end_y as isize);
 
             let start_x = cmp::max(0, cmp::min(self.width as isize - 1, point.x)) as usize * 4;
             let len = cmp::max(0, cmp::min(self.width as isize - 1, point.x + size.width as isize)) as usize *
@@ -225,6 +328,7 @@ impl Display {
         unsafe {
             if point.x >= 0 && point.x < self.width as isize && point.y >= 0 &&
                point.y < self.height as isize {
+                self.damage(point.y, point.y + 1);
                 *((self.offscreen + point.y as usize * self.bytesperrow + point.x as usize * 4) as *mut u32) = color.data;
             }
         }
@@ -336,6 +440,7 @@ impl Display {
     pub unsafe fn image(&self, point: Point, data: *const Color, size: Size) {
         let start_y = cmp::max(0, point.y) as usize;
         let end_y = cmp::min(self.height as isize, point.y + size.height as isize) as usize;
+        self.damage(start_y as isize, end_y as isize);
 
         let start_x = cmp::max(0, point.x) as usize;
         let len = cmp::min(self.width as isize, point.x + size.width as isize) as usize * 4 -
@@ -354,10 +459,36 @@ impl Display {
     }
     /* } Optimized */
 
+    /// Draw an image, leaving the pixels that are exactly `key` transparent
+    pub unsafe fn image_key(&self, point: Point, data: *const Color, size: Size, key: Color) {
+        let start_y = cmp::max(0, point.y) as usize;
+        let end_y = cmp::min(self.height as isize, point.y + size.height as isize) as usize;
+        self.damage(start_y as isize, end_y as isize);
+
+        let start_x = cmp::max(0, point.x) as usize;
+        let end_x = cmp::min(self.width as isize, point.x + size.width as isize) as usize;
+
+        let bytesperrow = size.width * 4;
+        let data_offset = data as usize - start_y * bytesperrow -
+                          (point.x - start_x as isize) as usize * 4;
+
+        for y in start_y..end_y {
+            let src = data_offset + y * bytesperrow;
+            let dst = self.offscreen + y * self.bytesperrow;
+            for x in start_x..end_x {
+                let pixel = *((src + x * 4) as *const u32);
+                if pixel != key.data {
+                    *((dst + x * 4) as *mut u32) = pixel;
+                }
+            }
+        }
+    }
+
     /// Draw a image with opacity
     pub unsafe fn image_alpha(&self, point: Point, data: *const Color, size: Size) {
         let start_y = cmp::max(0, point.y) as usize;
         let end_y = cmp::min(self.height as isize, point.y + size.height as isize) as usize;
+        self.damage(start_y as isize, end_y as isize);
 
         let start_x = cmp::max(0, point.x) as usize;
         let len = cmp::min(self.width as isize, point.x + size.width as isize) as usize * 4 -
@@ -450,6 +581,8 @@ impl Drop for Display {
             self.width = 0;
             self.height = 0;
             self.root = false;
+            self.damage_start.set(0);
+            self.damage_end.set(0);
         }
     }
 }
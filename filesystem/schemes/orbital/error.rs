@@ -0,0 +1,24 @@
+/// Why a request to the orbital scheme failed
+///
+/// Scheme calls can only report failure as a whole, so the reason is logged where the call
+/// returns
+#[derive(Copy, Clone, PartialEq)]
+pub enum Error {
+    /// A setting, or its value, was not understood
+    InvalidArgument,
+    /// A write went past the end of the window content
+    OutOfRange,
+    /// The display does not support the requested mode
+    Unsupported,
+}
+
+impl Error {
+    /// Describe the error
+    pub fn description(&self) -> &'static str {
+        match *self {
+            Error::InvalidArgument => "invalid argument",
+            Error::OutOfRange => "out of range",
+            Error::Unsupported => "unsupported",
+        }
+    }
+}
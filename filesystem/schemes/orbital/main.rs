@@ -1,88 +1,262 @@
-use redox::{Box, String, Url};
-use redox::{cmp, mem, ptr};
+use redox::{Box, String, ToString, Url};
+use redox::{cmp, str};
 use redox::fs::File;
 use redox::get_slice::GetSlice;
+use redox::glob::glob_match;
 use redox::io::*;
 use redox::ops::DerefMut;
 use redox::to_num::ToNum;
 
-use orbital::event::Event;
+use orbital::event::{Event, EVENT_SIZE};
+use orbital::{Color, PixelFormat};
 use orbital::Point;
 use orbital::Size;
+use orbital::window::CONTROL_PREFIX;
 
 use self::display::Display;
+use self::error::Error;
 use self::session::Session;
 use self::window::Window;
 
 pub mod display;
+pub mod error;
 pub mod package;
 pub mod scheduler;
 pub mod session;
+pub mod theme;
 pub mod window;
 
 pub static mut session_ptr: *mut Session = 0 as *mut Session;
 
-/// A window resource
+/// The kind of an orbital resource
+pub enum ResourceKind {
+    /// A window
+    Window(Box<Window>),
+    /// The display settings
+    Settings,
+    /// Information about windows, taken when it was opened
+    Info(String),
+    /// The input devices
+    Input,
+}
+
+/// An orbital resource
 pub struct Resource {
-    /// The window
-    pub window: Box<Window>,
+    /// The kind of resource
+    pub kind: ResourceKind,
     /// Seek point
     pub seek: usize,
 }
 
 impl Resource {
     pub fn dup(&self) -> Option<Box<Resource>> {
-        Some(box Resource {
-            window: Window::new(self.window.point, self.window.size, self.window.title.clone()),
-            seek: self.seek,
-        })
+        match self.kind {
+            ResourceKind::Window(ref window) => {
+                let mut dup_window = Window::new(window.point, window.size, window.title.clone(), false);
+                dup_window.format = window.format;
+                dup_window.event_mask = window.event_mask;
+                dup_window.icon = window.icon.clone();
+                dup_window.icon_size = window.icon_size;
+                dup_window.color_key = window.color_key;
+                Some(box Resource {
+                    kind: ResourceKind::Window(dup_window),
+                    seek: self.seek,
+                })
+            }
+            ResourceKind::Settings => Some(box Resource {
+                kind: ResourceKind::Settings,
+                seek: self.seek,
+            }),
+            ResourceKind::Info(ref info) => Some(box Resource {
+                kind: ResourceKind::Info(info.clone()),
+                seek: self.seek,
+            }),
+            ResourceKind::Input => Some(box Resource {
+                kind: ResourceKind::Input,
+                seek: self.seek,
+            }),
+        }
     }
 
     /// Return the url of this resource
     pub fn path(&self) -> Option<String> {
-        Some(format!("orbital:///{}/{}/{}/{}/{}",
-                         self.window.point.x,
-                         self.window.point.y,
-                         self.window.size.width,
-                         self.window.size.height,
-                         self.window.title))
+        match self.kind {
+            ResourceKind::Window(ref window) => Some(format!("orbital://{}/{}/{}/{}/{}/{}",
+                                                            if window.format == PixelFormat::Bgra {
+                                                                ""
+                                                            } else {
+                                                                window.format.name()
+                                                            },
+                                                            window.point.x,
+                                                            window.point.y,
+                                                            window.size.width,
+                                                            window.size.height,
+                                                            window.title)),
+            ResourceKind::Settings => Some("orbital://display/".to_string()),
+            ResourceKind::Info(_) => Some("orbital://info/".to_string()),
+            ResourceKind::Input => Some("orbital://input/".to_string()),
+        }
     }
 
     /// Read data to buffer
     pub fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
-        //Read events from window
-        let mut i = 0;
-        while buf.len() - i >= mem::size_of::<Event>() {
-            match self.window.poll() {
-                Some(event) => {
-                    unsafe { ptr::write(buf.as_ptr().offset(i as isize) as *mut Event, event) };
-                    i += mem::size_of::<Event>();
+        match self.kind {
+            ResourceKind::Window(ref mut window) => {
+                //Read events from window
+                let mut i = 0;
+                while buf.len() - i >= EVENT_SIZE {
+                    match window.poll() {
+                        Some(event) => {
+                            for b in event.to_bytes().iter() {
+                                buf[i] = *b;
+                                i += 1;
+                            }
+                        }
+                        None => break,
+                    }
                 }
-                None => break,
+
+                Some(i)
             }
-        }
+            ResourceKind::Settings | ResourceKind::Info(_) | ResourceKind::Input => {
+                let text = match self.kind {
+                    ResourceKind::Info(ref info) => info.clone(),
+                    ResourceKind::Input => unsafe { (*session_ptr).input_info() },
+                    _ => unsafe { (*session_ptr).settings() },
+                };
+
+                let mut i = 0;
+                for b in text.bytes().skip(self.seek) {
+                    if i < buf.len() {
+                        buf[i] = b;
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                self.seek += i;
 
-        Some(i)
+                Some(i)
+            }
+        }
     }
 
     /// Write to resource
     pub fn write(&mut self, buf: &[u8]) -> Option<usize> {
-        let content = &mut self.window.content;
-
-        let size = cmp::min(content.size - self.seek, buf.len());
-        unsafe {
-            Display::copy_run(buf.as_ptr() as usize,
-                              content.offscreen + self.seek,
-                              size);
+        match self.write_inner(buf) {
+            Ok(size) => Some(size),
+            Err(err) => {
+                debugln!("Write of {} bytes to {} failed: {}",
+                         buf.len(),
+                         self.path().unwrap_or(String::new()),
+                         err.description());
+                None
+            }
         }
-        self.seek += size;
+    }
 
-        return Some(size);
+    fn write_inner(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        match self.kind {
+            ResourceKind::Window(ref mut window) => {
+                //Writes behind the control prefix are settings, anything else is content
+                if buf.starts_with(CONTROL_PREFIX) {
+                    let control = buf.get_slice(Some(CONTROL_PREFIX.len()), None);
+                    if control.starts_with(b"icon=") {
+                        return window.set_icon(control).map(|_| buf.len());
+                    }
+
+                    let settings = match str::from_utf8(control) {
+                        Ok(settings) => settings,
+                        Err(_) => return Err(Error::InvalidArgument),
+                    };
+                    for line in settings.lines() {
+                        if line.starts_with("events=") {
+                            window.event_mask = line.get_slice(Some(7), None).trim().to_num();
+                        } else if line.starts_with("color_key=") {
+                            let value = line.get_slice(Some(10), None).trim();
+                            window.color_key = if value.is_empty() {
+                                None
+                            } else {
+                                let data = value.to_num_radix(16) as u32;
+                                Some(Color { data: if window.format == PixelFormat::Rgba {
+                                    (data & 0xFF00FF00) | (data >> 16 & 0xFF) | (data & 0xFF) << 16
+                                } else {
+                                    data
+                                } })
+                            };
+                            unsafe { (*session_ptr).redraw = true; }
+                        } else if !line.trim().is_empty() {
+                            debugln!("Unknown window setting: {}", line);
+                            return Err(Error::InvalidArgument);
+                        }
+                    }
+                    return Ok(buf.len());
+                }
+
+                let swap = window.format == PixelFormat::Rgba;
+                match window.content.write_offscreen(self.seek, buf, swap) {
+                    Some(size) => {
+                        self.seek += size;
+                        Ok(size)
+                    }
+                    None => Err(Error::OutOfRange),
+                }
+            }
+            ResourceKind::Settings => {
+                let session = unsafe { &mut *session_ptr };
+
+                // Check every line against copies first, so a rejected write changes nothing
+                let mut width = session.display.width;
+                let mut height = session.display.height;
+                let mut tick_interval = session.tick_interval;
+                let mut theme = session.theme.clone();
+                let settings = match str::from_utf8(buf) {
+                    Ok(settings) => settings,
+                    Err(_) => return Err(Error::InvalidArgument),
+                };
+                for line in settings.lines() {
+                    if line.starts_with("width=") {
+                        width = line.get_slice(Some(6), None).trim().to_num();
+                    } else if line.starts_with("height=") {
+                        height = line.get_slice(Some(7), None).trim().to_num();
+                    } else if line.starts_with("tick=") {
+                        tick_interval = line.get_slice(Some(5), None).trim().to_num() as u64;
+                    } else if let Some(i) = line.find('=') {
+                        let key = line.get_slice(None, Some(i)).trim();
+                        let value = line.get_slice(Some(i + 1), None).trim();
+                        if !theme.set(key, value) {
+                            debugln!("Invalid display setting: {}", line);
+                            return Err(Error::InvalidArgument);
+                        }
+                    } else if !line.trim().is_empty() {
+                        debugln!("Unknown display setting: {}", line);
+                        return Err(Error::InvalidArgument);
+                    }
+                }
+
+                if (width != session.display.width || height != session.display.height) &&
+                   !unsafe { session.set_viewport(width, height) } {
+                    return Err(Error::Unsupported);
+                }
+
+                session.tick_interval = tick_interval;
+                session.theme = theme;
+                session.redraw = true;
+                unsafe { session.redraw() };
+                Ok(buf.len())
+            }
+            ResourceKind::Info(_) | ResourceKind::Input => Err(Error::InvalidArgument),
+        }
     }
 
     /// Seek
     pub fn seek(&mut self, pos: SeekFrom) -> Option<usize> {
-        let end = self.window.content.size;
+        let end = match self.kind {
+            ResourceKind::Window(ref window) => window.content.size,
+            ResourceKind::Settings => unsafe { (*session_ptr).settings().len() },
+            ResourceKind::Info(ref info) => info.len(),
+            ResourceKind::Input => unsafe { (*session_ptr).input_info().len() },
+        };
 
         self.seek = match pos {
             SeekFrom::Start(offset) => cmp::min(end, cmp::max(0, offset)),
@@ -95,11 +269,23 @@ impl Resource {
 
     /// Sync the resource, should flip
     pub fn sync(&mut self) -> bool {
-        self.window.redraw();
+        match self.kind {
+            ResourceKind::Window(ref mut window) => window.redraw(),
+            ResourceKind::Settings | ResourceKind::Info(_) | ResourceKind::Input => (),
+        }
         true
     }
 }
 
+/// Parse a window dimension, either in pixels or as a percentage of the display, like `50%`
+fn dimension(part: &str, display: usize) -> usize {
+    if part.ends_with('%') {
+        display * part.get_slice(None, Some(part.len() - 1)).to_num() / 100
+    } else {
+        part.to_num()
+    }
+}
+
 /// A window scheme
 pub struct Scheme {
     pub session: Box<Session>,
@@ -126,7 +312,24 @@ impl Scheme {
         let url = Url::from_str(url_str);
 
         let host = url.host();
-        if host.is_empty() {
+        //The host of a window is the pixel format of its content, native if empty, followed by
+        //flags such as +background, which opens the window below the others without focus
+        let mut host_parts = host.split('+');
+        let format_name = host_parts.next().unwrap_or("");
+        let format = if format_name.is_empty() {
+            Some(PixelFormat::Bgra)
+        } else {
+            PixelFormat::from_name(format_name)
+        };
+        let mut raise = true;
+        for flag in host_parts {
+            if flag == "background" {
+                raise = false;
+            } else {
+                debugln!("Unknown window flag: {}", flag);
+            }
+        }
+        if let Some(format) = format {
             let path = url.path_parts();
             let mut pointx = match path.get(0) {
                 Some(x) => x.to_num_signed(),
@@ -137,11 +340,11 @@ impl Scheme {
                 None => 0,
             };
             let size_width = match path.get(2) {
-                Some(w) => w.to_num(),
+                Some(w) => dimension(w, self.session.display.width),
                 None => 100,
             };
             let size_height = match path.get(3) {
-                Some(h) => h.to_num(),
+                Some(h) => dimension(h, self.session.display.height),
                 None => 100,
             };
 
@@ -169,10 +372,44 @@ impl Scheme {
                 pointy = self.next_y;
             }
 
+            let mut window = Window::new(Point::new(pointx, pointy), Size::new(size_width, size_height), title, raise);
+            window.format = format;
+            Some(box Resource {
+                kind: ResourceKind::Window(window),
+                seek: 0,
+            })
+        } else if host == "display" {
             Some(box Resource {
-                window: Window::new(Point::new(pointx, pointy), Size::new(size_width, size_height), title),
+                kind: ResourceKind::Settings,
                 seek: 0,
             })
+        } else if host == "input" {
+            Some(box Resource {
+                kind: ResourceKind::Input,
+                seek: 0,
+            })
+        } else if host == "info" {
+            //orbital://info/ lists every window, orbital://info/id a single one
+            let id = url.path_parts().get(0).map(|id| id.to_num());
+            let mut info = String::new();
+            unsafe {
+                let reenable = scheduler::start_no_ints();
+                for window_ptr in self.session.windows_ordered.iter() {
+                    if id.map_or(true, |id| id == (**window_ptr).id) {
+                        info = info + &(**window_ptr).info();
+                    }
+                }
+                scheduler::end_no_ints(reenable);
+            }
+
+            if info.is_empty() && id.is_some() {
+                None
+            } else {
+                Some(box Resource {
+                    kind: ResourceKind::Info(info),
+                    seek: 0,
+                })
+            }
         } else if host == "launch" {
             let path = url.path();
 
@@ -182,9 +419,7 @@ impl Scheme {
                 for package in self.session.packages.iter() {
                     let mut accepted = false;
                     for accept in package.accepts.iter() {
-                        if (accept.starts_with('*') && path.ends_with(&accept.get_slice(Some(1), None)))
-                        || (accept.ends_with('*') && path.starts_with(&accept.get_slice(None, Some(accept.len() - 1))))
-                        {
+                        if glob_match(accept, &path) {
                             accepted = true;
                             break;
                         }
@@ -224,3 +459,67 @@ impl Scheme {
 pub unsafe extern "C" fn _event(scheme: *mut Scheme, event: *const Event) {
     (*scheme).event(&*event);
 }
+
+#[cfg(test)]
+mod tests {
+    use redox::{String, ToString, Vec};
+    use redox::ops::DerefMut;
+
+    use orbital::{Point, Size};
+    use orbital::window::CONTROL_PREFIX;
+
+    use super::{session_ptr, Resource, ResourceKind};
+    use super::display::Display;
+    use super::error::Error;
+    use super::session::Session;
+    use super::window::Window;
+
+    fn control(settings: &str) -> Vec<u8> {
+        let mut vec = CONTROL_PREFIX.to_vec();
+        vec.push_all(settings.as_bytes());
+        vec
+    }
+
+    #[test]
+    fn write_read_only() {
+        let mut info = Resource { kind: ResourceKind::Info(String::new()), seek: 0 };
+        assert!(info.write_inner(b"id=1") == Err(Error::InvalidArgument));
+
+        let mut input = Resource { kind: ResourceKind::Input, seek: 0 };
+        assert!(input.write_inner(b"keyboard=1") == Err(Error::InvalidArgument));
+    }
+
+    #[test]
+    fn write_errors() {
+        // A display that is not the root one can not change its viewport, and is never on screen
+        let mut session = Session::new();
+        session.display = Display::new(64, 48);
+        unsafe { session_ptr = session.deref_mut() };
+
+        {
+            let window = Window::new(Point::new(0, 0), Size::new(2, 2), "test".to_string(), false);
+            let mut resource = Resource { kind: ResourceKind::Window(window), seek: 0 };
+            assert!(resource.write_inner(&control("unknown=1")) == Err(Error::InvalidArgument));
+            assert!(resource.write_inner(&control("icon=2/2\n")) == Err(Error::InvalidArgument));
+            assert!(resource.write_inner(&control("events=1")) == Ok(CONTROL_PREFIX.len() + 8));
+
+            resource.seek = 2;
+            assert!(resource.write_inner(&[0; 4]) == Err(Error::OutOfRange));
+            resource.seek = 20;
+            assert!(resource.write_inner(&[0; 4]) == Err(Error::OutOfRange));
+        }
+
+        let mut settings = Resource { kind: ResourceKind::Settings, seek: 0 };
+        assert!(settings.write_inner(b"unknown") == Err(Error::InvalidArgument));
+        assert!(settings.write_inner(&[0xFF]) == Err(Error::InvalidArgument));
+        assert!(settings.write_inner(b"shadow=x") == Err(Error::InvalidArgument));
+
+        // The title height is only applied if the whole write is
+        let title_height = session.theme.title_height;
+        assert!(settings.write_inner(b"title_height=30\nwidth=32\nheight=24") == Err(Error::Unsupported));
+        assert_eq!(session.theme.title_height, title_height);
+        assert_eq!(session.display.width, 64);
+
+        unsafe { session_ptr = 0 as *mut Session };
+    }
+}
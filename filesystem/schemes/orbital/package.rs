@@ -14,6 +14,8 @@ pub struct Package {
     pub name: String,
     /// The binary for the package
     pub binary: String,
+    /// The path of the icon
+    pub icon_path: String,
     /// The icon for the package
     pub icon: BmpFile,
     /// The accepted extensions
@@ -22,6 +24,8 @@ pub struct Package {
     pub authors: Vec<String>,
     /// The description of the package
     pub descriptions: Vec<String>,
+    /// The categories of the package, such as `Utilities`
+    pub categories: Vec<String>,
 }
 
 impl Package {
@@ -32,10 +36,12 @@ impl Package {
             id: String::new(),
             name: String::new(),
             binary: url.to_string() + "main.bin",
+            icon_path: String::new(),
             icon: BmpFile::default(),
             accepts: Vec::new(),
             authors: Vec::new(),
             descriptions: Vec::new(),
+            categories: Vec::new(),
         };
 
         for part in url.to_string().rsplit('/') {
@@ -57,7 +63,8 @@ impl Package {
             } else if line.starts_with("binary=") {
                 package.binary = url.to_string() + line.get_slice(Some(7), None);
             } else if line.starts_with("icon=") {
-                if let Some(mut file) = File::open(line.get_slice(Some(5), None)) {
+                package.icon_path = line.get_slice(Some(5), None).to_string();
+                if let Some(mut file) = File::open(&package.icon_path) {
                     let mut vec: Vec<u8> = Vec::new();
                     file.read_to_end(&mut vec);
                     package.icon = BmpFile::from_data(&vec);
@@ -68,11 +75,18 @@ impl Package {
                 package.authors.push(line.get_slice(Some(7), None).to_string());
             } else if line.starts_with("description=") {
                 package.descriptions.push(line.get_slice(Some(12), None).to_string());
-            } else {
+            } else if line.starts_with("category=") {
+                package.categories.push(line.get_slice(Some(9), None).to_string());
+            } else if !line.trim().is_empty() {
                 debugln!("Unknown package info: {}", line);
             }
         }
 
+        //Packages without a name are shown by their ID
+        if package.name.is_empty() {
+            package.name = package.id.clone();
+        }
+
         package
     }
 }
@@ -1,14 +1,21 @@
 use redox::{Box, String, ToString, Vec, Url};
+use redox::cmp;
 use redox::fs::File;
 use redox::io::Read;
 
 use orbital::{BmpFile, Color, Point, Size, Event, EventOption, KeyEvent, MouseEvent};
 
-use super::display::Display;
+use super::display::{self, Display};
 use super::package::*;
 use super::scheduler;
+use super::theme::Theme;
 use super::window::Window;
 
+/// The width of a character in the task bar
+const CHAR_WIDTH: usize = 8;
+/// The space taken by a window icon in the task bar, as wide as this many characters
+const TASK_ICON_CHARS: usize = 3;
+
 /// A session
 pub struct Session {
     /// The display
@@ -19,6 +26,8 @@ pub struct Session {
     pub cursor: BmpFile,
     /// The background image
     pub background: BmpFile,
+    /// The icon of windows without their own
+    pub default_icon: BmpFile,
     /// The last mouse event
     pub last_mouse_event: MouseEvent,
     /// The packages (applications)
@@ -27,8 +36,18 @@ pub struct Session {
     pub windows: Vec<*mut Window>,
     /// Ordered windows
     pub windows_ordered: Vec<*mut Window>,
+    /// The theme of the window decorations
+    pub theme: Theme,
+    /// The time between ticks sent to windows that ask for them, in milliseconds, 0 to stop them
+    pub tick_interval: u64,
     /// Redraw
     pub redraw: bool,
+    /// The id for the next window
+    window_id: usize,
+    /// How many key events have been received
+    key_events: usize,
+    /// How many mouse events have been received
+    mouse_events: usize,
 }
 
 impl Session {
@@ -39,6 +58,7 @@ impl Session {
             font: Vec::new(),
             cursor: BmpFile::default(),
             background: BmpFile::default(),
+            default_icon: BmpFile::default(),
             last_mouse_event: MouseEvent {
                 x: 0,
                 y: 0,
@@ -49,7 +69,12 @@ impl Session {
             packages: Vec::new(),
             windows: Vec::new(),
             windows_ordered: Vec::new(),
+            theme: Theme::new(),
+            tick_interval: 500,
             redraw: true,
+            window_id: 1,
+            key_events: 0,
+            mouse_events: 0,
         };
 
         if let Some(mut file) = File::open("file:/ui/unifont.font") {
@@ -60,6 +85,12 @@ impl Session {
             debugln!("Failed to read font");
         }
 
+        if let Some(mut file) = File::open("file:/ui/theme") {
+            let mut string = String::new();
+            file.read_to_string(&mut string);
+            ret.theme.load(&string);
+        }
+
         if let Some(mut file) = File::open("file:/ui/cursor.bmp") {
             let mut vec = Vec::new();
             file.read_to_end(&mut vec);
@@ -76,6 +107,14 @@ impl Session {
             debugln!("Failed to read background");
         }
 
+        if let Some(mut file) = File::open("file:/ui/mimetypes/application-x-executable.bmp") {
+            let mut vec = Vec::new();
+            file.read_to_end(&mut vec);
+            ret.default_icon = BmpFile::from_data(&vec);
+        } else {
+            debugln!("Failed to read default icon");
+        }
+
         if let Some(mut file) = File::open("file:/apps/") {
             let mut string = String::new();
             file.read_to_string(&mut string);
@@ -92,8 +131,20 @@ impl Session {
         ret
     }
 
-    pub unsafe fn add_window(&mut self, add_window_ptr: *mut Window) {
-        self.windows.push(add_window_ptr);
+    /// Get a new window id
+    pub fn next_window_id(&mut self) -> usize {
+        let id = self.window_id;
+        self.window_id += 1;
+        id
+    }
+
+    /// Add a window, on top and focused if `raise` is set, otherwise at the bottom
+    pub unsafe fn add_window(&mut self, add_window_ptr: *mut Window, raise: bool) {
+        if raise {
+            self.windows.push(add_window_ptr);
+        } else {
+            self.windows.insert(0, add_window_ptr);
+        }
         self.windows_ordered.push(add_window_ptr);
         self.redraw = true;
     }
@@ -139,6 +190,46 @@ impl Session {
         self.redraw = true;
     }
 
+    /// Describe the display settings and theme, as `key=value` lines
+    pub fn settings(&self) -> String {
+        let root_size = unsafe { Display::root_size() };
+
+        let mut modes = String::new();
+        for &(width, height) in display::MODES.iter() {
+            if width <= root_size.width && height <= root_size.height {
+                if !modes.is_empty() {
+                    modes.push(' ');
+                }
+                modes = modes + &format!("{}x{}", width, height);
+            }
+        }
+
+        format!("width={}\nheight={}\nmodes={}\ntick={}\n",
+                self.display.width, self.display.height, modes, self.tick_interval) +
+        &self.theme.settings()
+    }
+
+    /// Change the viewport size, moving windows back inside the new bounds
+    pub unsafe fn set_viewport(&mut self, width: usize, height: usize) -> bool {
+        if !self.display.set_viewport(width, height) {
+            return false;
+        }
+
+        for window_ptr in self.windows.iter() {
+            let window = &mut **window_ptr;
+            let max_x = cmp::max(0, width as isize - window.size.width as isize);
+            let title_height = self.theme.title_height as isize;
+            let max_y = cmp::max(title_height, height as isize - window.size.height as isize);
+            window.point.x = cmp::max(0, cmp::min(max_x, window.point.x));
+            window.point.y = cmp::max(title_height, cmp::min(max_y, window.point.y));
+        }
+
+        self.redraw = true;
+        self.redraw();
+
+        true
+    }
+
     fn on_key(&mut self, key_event: KeyEvent) {
         if !self.windows.is_empty() {
             match self.windows.get(self.windows.len() - 1) {
@@ -252,7 +343,7 @@ impl Session {
                 match self.windows.get(i) {
                     Some(window_ptr) => {
                         (**window_ptr).focused = i == self.windows.len() - 1;
-                        (**window_ptr).draw(&self.display, self.font.as_ptr() as usize);
+                        (**window_ptr).draw(&self.display, self.font.as_ptr() as usize, &self.theme);
                     }
                     None => (),
                 }
@@ -306,7 +397,11 @@ impl Session {
                                   (**window_ptr).border_color);
                 x += 4;
 
-                let mut i = 0;
+                (**window_ptr).draw_icon(&self.display, Point::new(x, self.display.height as isize - 24), 16);
+                x += (TASK_ICON_CHARS * CHAR_WIDTH) as isize;
+
+                //The icon counts towards the characters of the title
+                let mut i = TASK_ICON_CHARS;
                 for c in (**window_ptr).title.chars() {
                     if c != '\0' {
                         self.display.char(Point::new(x, self.display.height as isize - 24),
@@ -350,9 +445,27 @@ impl Session {
 
     pub fn event(&mut self, event: &Event) {
         match event.to_option() {
-            EventOption::Mouse(mouse_event) => self.on_mouse(mouse_event),
-            EventOption::Key(key_event) => self.on_key(key_event),
+            EventOption::Mouse(mouse_event) => {
+                self.mouse_events += 1;
+                self.on_mouse(mouse_event)
+            }
+            EventOption::Key(key_event) => {
+                self.key_events += 1;
+                self.on_key(key_event)
+            }
             _ => (),
         }
     }
+
+    /// Describe the input devices, as `key=value` lines
+    ///
+    /// Input comes from the kernel's PS/2 driver, which translates keys with a fixed US layout.
+    /// A device is only known to be present once it has sent an event.
+    pub fn input_info(&self) -> String {
+        format!("keyboard={}\nkeyboard_events={}\nlayout=us\nmouse={}\nmouse_events={}\n",
+                if self.key_events > 0 { "ps2" } else { "none" },
+                self.key_events,
+                if self.mouse_events > 0 { "ps2" } else { "none" },
+                self.mouse_events)
+    }
 }
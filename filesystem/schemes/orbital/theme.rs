@@ -0,0 +1,89 @@
+use redox::{cmp, String};
+use redox::get_slice::GetSlice;
+use redox::to_num::ToNum;
+
+use orbital::Color;
+
+/// The shortest title bar, which still fits the edges of the icon
+pub const TITLE_HEIGHT_MIN: usize = 2;
+/// The tallest title bar
+pub const TITLE_HEIGHT_MAX: usize = 64;
+
+/// The look of the window decorations
+#[derive(Clone)]
+pub struct Theme {
+    /// The height of the title bar
+    pub title_height: usize,
+    /// The color of the decorations of the focused window
+    pub active_color: Color,
+    /// The color of the decorations of other windows
+    pub inactive_color: Color,
+    /// The color of the title text
+    pub text_color: Color,
+    /// The width of the border around windows, 0 for no border
+    pub border_width: usize,
+    /// How far the shadow falls below and right of windows, 0 for no shadow
+    pub shadow: usize,
+}
+
+impl Theme {
+    /// Create the default theme
+    pub fn new() -> Self {
+        Theme {
+            title_height: 18,
+            active_color: Color::rgba(128, 128, 128, 192),
+            inactive_color: Color::rgba(64, 64, 64, 128),
+            text_color: Color::rgb(255, 255, 255),
+            border_width: 2,
+            shadow: 0,
+        }
+    }
+
+    /// Change a setting, with colors given in hexadecimal as `AARRGGBB`
+    /// The title height is kept between `TITLE_HEIGHT_MIN` and `TITLE_HEIGHT_MAX`
+    /// Returns false if the key is not a theme setting, or the value is not a number
+    pub fn set(&mut self, key: &str, value: &str) -> bool {
+        let radix = if key.ends_with("_color") { 16 } else { 10 };
+        if value.is_empty() || !value.chars().all(|c| c.is_digit(radix)) {
+            return false;
+        }
+
+        match key {
+            "title_height" => {
+                let title_height = cmp::min(TITLE_HEIGHT_MAX, value.to_num());
+                self.title_height = cmp::max(TITLE_HEIGHT_MIN, title_height);
+            }
+            "active_color" => self.active_color = Color { data: value.to_num_radix(16) as u32 },
+            "inactive_color" => self.inactive_color = Color { data: value.to_num_radix(16) as u32 },
+            "text_color" => self.text_color = Color { data: value.to_num_radix(16) as u32 },
+            "border_width" => self.border_width = value.to_num(),
+            "shadow" => self.shadow = value.to_num(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Load settings from `key=value` lines, ignoring unknown keys
+    pub fn load(&mut self, settings: &str) {
+        for line in settings.lines() {
+            if let Some(i) = line.find('=') {
+                let key = line.get_slice(None, Some(i)).trim();
+                let value = line.get_slice(Some(i + 1), None).trim();
+                if !self.set(key, value) {
+                    debugln!("Invalid theme setting: {}", line);
+                }
+            }
+        }
+    }
+
+    /// Describe the theme, as `key=value` lines
+    pub fn settings(&self) -> String {
+        format!("title_height={}\nactive_color={:08X}\ninactive_color={:08X}\ntext_color={:08X}\nborder_width={}\nshadow={}\n",
+                self.title_height,
+                self.active_color.data,
+                self.inactive_color.data,
+                self.text_color.data,
+                self.border_width,
+                self.shadow)
+    }
+}
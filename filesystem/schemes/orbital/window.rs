@@ -1,14 +1,26 @@
-use redox::{Box, String};
+use redox::{Box, String, Vec};
 use redox::collections::VecDeque;
+use redox::get_slice::GetSlice;
 use redox::ops::DerefMut;
+use redox::str;
+use redox::time;
+use redox::to_num::ToNum;
 
-use orbital::{Color, Point, Size, Event, KeyEvent, MouseEvent, QuitEvent};
+use orbital::{Color, PixelFormat, Point, Size, Event, KeyEvent, MouseEvent, QuitEvent, TickEvent};
+use orbital::event::{EVENT_ALL, EVENT_KEY, EVENT_MOUSE, EVENT_QUIT, EVENT_TICK};
 
 use super::display::Display;
+use super::error::Error;
 use super::scheduler;
+use super::theme::Theme;
+
+/// The largest width and height of an icon
+pub const ICON_MAX: usize = 256;
 
 /// A window
 pub struct Window {
+    /// The id of the window, unique in the session
+    pub id: usize,
     /// The position of the window
     pub point: Point,
     /// The size of the window
@@ -17,6 +29,16 @@ pub struct Window {
     pub title: String,
     /// The content of the window
     pub content: Box<Display>,
+    /// The pixel format the client writes the content in
+    pub format: PixelFormat,
+    /// The kinds of events the client wants to receive
+    pub event_mask: usize,
+    /// The icon set by the client, empty for the default icon
+    pub icon: Vec<Color>,
+    /// The size of the icon
+    pub icon_size: Size,
+    /// The color drawn as transparent, if any
+    pub color_key: Option<Color>,
     /// The color of the window title
     pub title_color: Color,
     /// The color of the border
@@ -27,23 +49,34 @@ pub struct Window {
     pub minimized: bool,
     dragging: bool,
     last_mouse_event: MouseEvent,
+    last_tick: u64,
+    tick_queued: bool,
     events: VecDeque<Event>,
     ptr: *mut Window,
 }
 
 impl Window {
     /// Create a new window
-    pub fn new(point: Point, size: Size, title: String) -> Box<Self> {
+    /// If `raise` is set, the window opens on top of the others and takes the focus
+    pub fn new(point: Point, size: Size, title: String, raise: bool) -> Box<Self> {
         let mut ret = box Window {
+            id: unsafe { (*super::session_ptr).next_window_id() },
             point: point,
             size: size,
             title: title,
             content: Display::new(size.width, size.height),
+            format: PixelFormat::Bgra,
+            event_mask: EVENT_ALL,
+            icon: Vec::new(),
+            icon_size: Size::new(0, 0),
+            color_key: None,
             title_color: Color::rgb(255, 255, 255),
             border_color: Color::rgba(64, 64, 64, 128),
             focused: false,
             minimized: false,
             dragging: false,
+            last_tick: 0,
+            tick_queued: false,
             last_mouse_event: MouseEvent {
                 x: 0,
                 y: 0,
@@ -59,24 +92,130 @@ impl Window {
             ret.ptr = ret.deref_mut();
 
             if ret.ptr as usize > 0 {
-                (*super::session_ptr).add_window(ret.ptr);
+                (*super::session_ptr).add_window(ret.ptr, raise);
             }
         }
 
         ret
     }
 
+    /// Describe the window as key=value lines, ending with an empty line
+    pub fn info(&self) -> String {
+        format!("id={}\nx={}\ny={}\nwidth={}\nheight={}\ntitle={}\nfocused={}\nminimized={}\n\n",
+                self.id,
+                self.point.x,
+                self.point.y,
+                self.size.width,
+                self.size.height,
+                self.title,
+                self.focused,
+                self.minimized)
+    }
+
     /// Poll the window (new)
     pub fn poll(&mut self) -> Option<Event> {
+        self.tick();
+
         let event_option;
         unsafe {
             let reenable = scheduler::start_no_ints();
             event_option = self.events.pop_front();
             scheduler::end_no_ints(reenable);
         }
+        if event_option.map_or(false, |event| event.code == 't') {
+            self.tick_queued = false;
+        }
         return event_option;
     }
 
+    /// Queue a tick if the window wants them and the tick interval has passed
+    /// Only one tick is queued at a time, so a window that stops reading does not pile them up
+    fn tick(&mut self) {
+        if self.event_mask & EVENT_TICK != EVENT_TICK || self.tick_queued {
+            return;
+        }
+
+        let now = time::monotonic_ms();
+        let interval = unsafe { (*super::session_ptr).tick_interval };
+        if interval > 0 && now - self.last_tick >= interval {
+            self.last_tick = now;
+            self.tick_queued = true;
+            unsafe {
+                let reenable = scheduler::start_no_ints();
+                self.events.push_back(TickEvent { time_ms: now }.to_event());
+                scheduler::end_no_ints(reenable);
+            }
+        }
+    }
+
+    /// Set the icon from an `icon=width/height` line followed by the pixels
+    pub fn set_icon(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let header_len = match buf.iter().position(|&b| b == b'\n') {
+            Some(i) => i,
+            None => return Err(Error::InvalidArgument),
+        };
+
+        let header = match str::from_utf8(buf.get_slice(Some(5), Some(header_len))) {
+            Ok(header) => header,
+            Err(_) => return Err(Error::InvalidArgument),
+        };
+        let mut parts = header.split('/');
+        let width = parts.next().unwrap_or("").to_num();
+        let height = parts.next().unwrap_or("").to_num();
+        if width > ICON_MAX || height > ICON_MAX {
+            debugln!("Invalid icon for {}: {}x{} is larger than {}x{}", self.title, width, height, ICON_MAX, ICON_MAX);
+            return Err(Error::InvalidArgument);
+        }
+
+        let pixels = buf.get_slice(Some(header_len + 1), None);
+        if width.checked_mul(height).and_then(|area| area.checked_mul(4)) != Some(pixels.len()) {
+            debugln!("Invalid icon for {}: {}x{} with {} bytes", self.title, width, height, pixels.len());
+            return Err(Error::InvalidArgument);
+        }
+
+        let swap = self.format == PixelFormat::Rgba;
+        self.icon = Vec::with_capacity(width * height);
+        for i in 0..width * height {
+            let data = pixels[i * 4] as u32 | (pixels[i * 4 + 1] as u32) << 8 |
+                       (pixels[i * 4 + 2] as u32) << 16 | (pixels[i * 4 + 3] as u32) << 24;
+            if swap {
+                self.icon.push(Color { data: (data & 0xFF00FF00) | (data >> 16 & 0xFF) | (data & 0xFF) << 16 });
+            } else {
+                self.icon.push(Color { data: data });
+            }
+        }
+        self.icon_size = Size::new(width, height);
+
+        unsafe { (*super::session_ptr).redraw = true; }
+
+        Ok(buf.len())
+    }
+
+    /// Draw the icon, or the default icon, scaled to a square of `side` pixels
+    pub fn draw_icon(&self, display: &Display, point: Point, side: usize) {
+        let (data, size) = if self.icon.is_empty() {
+            let default_icon = unsafe { &(*super::session_ptr).default_icon };
+            (default_icon.as_slice(), Size::new(default_icon.width(), default_icon.height()))
+        } else {
+            (&self.icon[..], self.icon_size)
+        };
+
+        if data.is_empty() || side == 0 {
+            return;
+        }
+
+        let mut scaled = Vec::with_capacity(side * side);
+        for y in 0..side {
+            for x in 0..side {
+                scaled.push(data[(y * size.height / side) * size.width + x * size.width / side]);
+            }
+        }
+
+        unsafe {
+            display.image_alpha(point, scaled.as_ptr(), Size::new(side, side));
+        }
+    }
+
     /// Redraw the window
     pub fn redraw(&mut self) {
         unsafe {
@@ -89,23 +228,38 @@ impl Window {
     }
 
     /// Draw the window using a `Display`
-    pub fn draw(&mut self, display: &Display, font: usize) {
+    pub fn draw(&mut self, display: &Display, font: usize, theme: &Theme) {
         if self.focused {
-            self.border_color = Color::rgba(128, 128, 128, 192);
+            self.border_color = theme.active_color;
         } else {
-            self.border_color = Color::rgba(64, 64, 64, 128);
+            self.border_color = theme.inactive_color;
         }
 
         if self.minimized {
             self.title_color = Color::rgb(0, 0, 0);
         } else {
-            self.title_color = Color::rgb(255, 255, 255);
+            self.title_color = theme.text_color;
+
+            let title_height = theme.title_height as isize;
+            let border = theme.border_width as isize;
+
+            if theme.shadow > 0 {
+                let shadow = theme.shadow as isize;
+                display.rect(Point::new(self.point.x - border + shadow, self.point.y - title_height + shadow),
+                             Size::new(self.size.width + 2 * theme.border_width,
+                                       self.size.height + theme.title_height + theme.border_width),
+                             Color::rgba(0, 0, 0, 64));
+            }
 
-            display.rect(Point::new(self.point.x - 2, self.point.y - 18),
-                         Size::new(self.size.width + 4, 18),
+            display.rect(Point::new(self.point.x - border, self.point.y - title_height),
+                         Size::new(self.size.width + 2 * theme.border_width, theme.title_height),
                          self.border_color);
 
-            let mut cursor = Point::new(self.point.x, self.point.y - 17);
+            let icon_side = theme.title_height.saturating_sub(2);
+            self.draw_icon(display, Point::new(self.point.x, self.point.y - title_height + 1), icon_side);
+
+            let mut cursor = Point::new(self.point.x + icon_side as isize + 4,
+                                        self.point.y - (title_height + 16) / 2);
             for c in self.title.chars() {
                 if cursor.x + 8 <= self.point.x + self.size.width as isize {
                     display.char(cursor, c, self.title_color, font);
@@ -113,23 +267,31 @@ impl Window {
                 cursor.x += 8;
             }
 
-            display.rect(Point::new(self.point.x - 2, self.point.y),
-                         Size::new(2, self.size.height),
-                         self.border_color);
-            display.rect(Point::new(self.point.x - 2,
-                                    self.point.y + self.size.height as isize),
-                         Size::new(self.size.width + 4, 2),
-                         self.border_color);
-            display.rect(Point::new(self.point.x + self.size.width as isize,
-                                    self.point.y),
-                         Size::new(2, self.size.height),
-                         self.border_color);
+            if border > 0 {
+                display.rect(Point::new(self.point.x - border, self.point.y),
+                             Size::new(theme.border_width, self.size.height),
+                             self.border_color);
+                display.rect(Point::new(self.point.x - border,
+                                        self.point.y + self.size.height as isize),
+                             Size::new(self.size.width + 2 * theme.border_width, theme.border_width),
+                             self.border_color);
+                display.rect(Point::new(self.point.x + self.size.width as isize,
+                                        self.point.y),
+                             Size::new(theme.border_width, self.size.height),
+                             self.border_color);
+            }
 
             unsafe {
                 let reenable = scheduler::start_no_ints();
-                display.image(self.point,
-                              self.content.onscreen as *const Color,
-                              Size::new(self.content.width, self.content.height));
+                match self.color_key {
+                    Some(key) => display.image_key(self.point,
+                                                   self.content.onscreen as *const Color,
+                                                   Size::new(self.content.width, self.content.height),
+                                                   key),
+                    None => display.image(self.point,
+                                          self.content.onscreen as *const Color,
+                                          Size::new(self.content.width, self.content.height)),
+                }
                 scheduler::end_no_ints(reenable);
             }
         }
@@ -137,6 +299,10 @@ impl Window {
 
     /// Called on key press
     pub fn on_key(&mut self, key_event: KeyEvent) {
+        if self.event_mask & EVENT_KEY != EVENT_KEY {
+            return;
+        }
+
         unsafe {
             let reenable = scheduler::start_no_ints();
             self.events.push_back(key_event.to_event());
@@ -144,11 +310,16 @@ impl Window {
         }
     }
 
+    /// Is the point on the title bar or the border?
     fn on_window_decoration(&self, x: isize, y: isize) -> bool {
-        !self.minimized && x >= -2 &&
-            x < self.size.width as isize + 4 &&
-            y >= -18 &&
-            y < 0
+        let theme = unsafe { &(*super::session_ptr).theme };
+        let title_height = theme.title_height as isize;
+        let border = theme.border_width as isize;
+        !self.minimized && !self.on_window_body(x, y) &&
+            x >= -border &&
+            x < self.size.width as isize + border &&
+            y >= -title_height &&
+            y < self.size.height as isize + border
     }
 
     fn on_window_body(&self, x: isize, y: isize) -> bool {
@@ -197,10 +368,12 @@ impl Window {
                     caught = true;
                 }else if self.on_window_decoration(mouse_event.x, mouse_event.y) {
                     caught = true;
-                    unsafe {
-                        let reenable = scheduler::start_no_ints();
-                        self.events.push_back(QuitEvent.to_event());
-                        scheduler::end_no_ints(reenable);
+                    if self.event_mask & EVENT_QUIT == EVENT_QUIT {
+                        unsafe {
+                            let reenable = scheduler::start_no_ints();
+                            self.events.push_back(QuitEvent.to_event());
+                            scheduler::end_no_ints(reenable);
+                        }
                     }
                 }
             }
@@ -216,7 +389,8 @@ impl Window {
 
         self.last_mouse_event = orig_mouse_event;
 
-        if (caught && !self.dragging) || self.on_window_body(mouse_event.x, mouse_event.y) {
+        if self.event_mask & EVENT_MOUSE == EVENT_MOUSE &&
+           ((caught && !self.dragging) || self.on_window_body(mouse_event.x, mouse_event.y)) {
             unsafe {
                 let reenable = scheduler::start_no_ints();
                 self.events.push_back(mouse_event.to_event());
@@ -12,7 +12,9 @@ pub struct Console {
     pub point: Point,
     pub draw: bool,
     pub redraw: bool,
-    pub command: Option<String>
+    pub command: Option<String>,
+    /// Set by Ctrl-C, until read from `debug:interrupt` or a new command is entered
+    pub interrupt: bool,
 }
 
 impl Console {
@@ -22,7 +24,8 @@ impl Console {
             point: Point::new(0, 0),
             draw: false,
             redraw: true,
-            command: None
+            command: None,
+            interrupt: false,
         }
     }
 
@@ -177,6 +177,7 @@ unsafe fn poll_loop() -> ! {
 unsafe fn event_loop() -> ! {
     let events = &mut *events_ptr;
     let mut cmd = String::new();
+    let mut ctrl = false;
     loop {
         loop {
             let reenable = scheduler::start_no_ints();
@@ -190,7 +191,9 @@ unsafe fn event_loop() -> ! {
                     if (*console).draw {
                         match event.to_option() {
                             EventOption::Key(key_event) => {
-                                if key_event.pressed {
+                                if key_event.scancode == event::K_CTRL {
+                                    ctrl = key_event.pressed;
+                                } else if key_event.pressed {
                                     match key_event.scancode {
                                         event::K_F2 => {
                                             (*console).draw = false;
@@ -201,9 +204,20 @@ unsafe fn event_loop() -> ! {
                                         },
                                         _ => match key_event.character {
                                             '\0' => (),
+                                            'c' if ctrl => {
+                                                // Ctrl-C discards the line and interrupts the running command
+                                                let reenable = scheduler::start_no_ints();
+                                                (*console).interrupt = true;
+                                                scheduler::end_no_ints(reenable);
+
+                                                cmd.clear();
+                                                debug::d("^C");
+                                                debug::dl();
+                                            }
                                             '\n' => {
                                                 let reenable = scheduler::start_no_ints();
                                                 (*console).command = Some(cmd.clone());
+                                                (*console).interrupt = false;
                                                 scheduler::end_no_ints(reenable);
 
                                                 cmd.clear();
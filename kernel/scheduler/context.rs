@@ -23,6 +23,19 @@ pub const CONTEXT_STACK_ADDR: usize = 0x70000000;
 pub static mut contexts_ptr: *mut Vec<Box<Context>> = 0 as *mut Vec<Box<Context>>;
 pub static mut context_i: usize = 0;
 pub static mut context_enabled: bool = false;
+/// The id of the next context created, ids are never reused
+pub static mut next_pid: usize = 1;
+
+/// Take an id for a new context
+///
+/// Unsafe due to interrupt disabling
+pub unsafe fn take_pid() -> usize {
+    let reenable = scheduler::start_no_ints();
+    let pid = next_pid;
+    next_pid += 1;
+    scheduler::end_no_ints(reenable);
+    pid
+}
 
 /// Switch context
 ///
@@ -118,6 +131,7 @@ pub unsafe extern "cdecl" fn context_clone(parent_ptr: *const Context, flags: us
                  CONTEXT_STACK_SIZE + 512);
 
         let context = box Context {
+            pid: take_pid(),
             name: parent.name.clone(),
             interrupted: parent.interrupted,
             exited: parent.exited,
@@ -267,6 +281,8 @@ pub struct ContextFile {
 
 pub struct Context {
 // These members are used for control purposes by the scheduler {
+/// The id of the context, which stays the same while other contexts come and go
+        pub pid: usize,
 // The name of the context
         pub name: String,
 /// Indicates that the context was interrupted, used for prioritizing active contexts
@@ -305,6 +321,7 @@ pub struct Context {
 impl Context {
     pub unsafe fn root() -> Box<Self> {
         box Context {
+            pid: 0,
             name: "kidle".to_string(),
             interrupted: false,
             exited: false,
@@ -327,6 +344,7 @@ impl Context {
         let kernel_stack = memory::alloc(CONTEXT_STACK_SIZE + 512);
 
         let mut ret = box Context {
+            pid: take_pid(),
             name: name,
             interrupted: false,
             exited: false,
@@ -1,5 +1,7 @@
 use alloc::boxed::Box;
 
+use common::to_num::ToNum;
+
 use scheduler::context;
 use scheduler;
 
@@ -12,8 +14,31 @@ impl KScheme for ContextScheme {
         "context"
     }
 
-    fn open(&mut self, _: &Url, _: usize) -> Option<Box<Resource>> {
-        let mut string = format!("{:<6}{:<8}{:<6}{}", "PID", "MEM", "FDS", "NAME");
+    fn open(&mut self, url: &Url, _: usize) -> Option<Box<Resource>> {
+        let reference = url.reference();
+        if !reference.is_empty() {
+            if !reference.chars().all(|c| c >= '0' && c <= '9') {
+                return None;
+            }
+
+            // Only programs can be killed, kernel contexts have no userspace stack
+            let pid = reference.to_num();
+            let killable = unsafe {
+                let reenable = scheduler::start_no_ints();
+                let killable = (*context::contexts_ptr).iter().any(|context| {
+                    context.pid == pid && context.stack.is_some()
+                });
+                scheduler::end_no_ints(reenable);
+                killable
+            };
+            if !killable {
+                return None;
+            }
+
+            return Some(box ContextResource { pid: pid });
+        }
+
+        let mut string = format!("{:<6}{:<8}{:<6}{:<9}{}", "PID", "MEM", "FDS", "STATE", "NAME");
         unsafe {
             let reenable = scheduler::start_no_ints();
             let mut i = 0;
@@ -33,10 +58,19 @@ impl KScheme for ContextScheme {
                     format!("{} B", memory)
                 };
 
-                let line = format!("{:<6}{:<8}{:<6}{}",
-                                   i,
+                let state = if context.exited {
+                    "Exited"
+                } else if i == context::context_i {
+                    "Running"
+                } else {
+                    "Ready"
+                };
+
+                let line = format!("{:<6}{:<8}{:<6}{:<9}{}",
+                                   context.pid,
                                    memory_string,
                                    (*context.files.get()).len(),
+                                   state,
                                    context.name);
 
                 string = string + "\n" + &line;
@@ -48,3 +82,39 @@ impl KScheme for ContextScheme {
         Some(box VecResource::new(Url::from_str("context:"), string.into_bytes()))
     }
 }
+
+/// A program, opened as `context:pid`, with the id listed by `context:`
+/// Writing `kill` makes it exit, it is removed the next time it would be switched to
+pub struct ContextResource {
+    pid: usize,
+}
+
+impl Resource for ContextResource {
+    fn url(&self) -> Url {
+        Url::from_string(format!("context:{}", self.pid))
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        if buf != b"kill" {
+            return None;
+        }
+
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            let mut killed = false;
+            for context in (*context::contexts_ptr).iter_mut() {
+                if context.pid == self.pid {
+                    context.exited = true;
+                    killed = true;
+                }
+            }
+            scheduler::end_no_ints(reenable);
+
+            if killed {
+                Some(buf.len())
+            } else {
+                None
+            }
+        }
+    }
+}
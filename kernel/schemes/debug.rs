@@ -89,6 +89,58 @@ impl Resource for DebugResource {
     }
 }
 
+/// The Ctrl-C flag of the console, opened as `debug:interrupt`
+/// Reading does not block, it gives 1 if Ctrl-C was pressed since the last check, and clears
+/// it, or nothing otherwise. Writing sets it, for consoles that see Ctrl-C themselves
+pub struct InterruptResource {
+    read: bool,
+}
+
+impl Resource for InterruptResource {
+    fn dup(&self) -> Option<Box<Resource>> {
+        Some(box InterruptResource { read: self.read })
+    }
+
+    fn url(&self) -> Url {
+        Url::from_str("debug:interrupt")
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        if self.read || buf.is_empty() {
+            return Some(0);
+        }
+        self.read = true;
+
+        let interrupt = unsafe {
+            let reenable = scheduler::start_no_ints();
+            let interrupt = (*::console).interrupt;
+            (*::console).interrupt = false;
+            scheduler::end_no_ints(reenable);
+            interrupt
+        };
+
+        if interrupt {
+            buf[0] = b'1';
+            Some(1)
+        } else {
+            Some(0)
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            (*::console).interrupt = true;
+            scheduler::end_no_ints(reenable);
+        }
+        Some(buf.len())
+    }
+
+    fn sync(&mut self) -> bool {
+        true
+    }
+}
+
 pub struct DebugScheme {
     pub context: usize,
 }
@@ -104,7 +156,11 @@ impl KScheme for DebugScheme {
         "debug"
     }
 
-    fn open(&mut self, _: &Url, _: usize) -> Option<Box<Resource>> {
+    fn open(&mut self, url: &Url, _: usize) -> Option<Box<Resource>> {
+        if url.reference() == "interrupt" {
+            return Some(box InterruptResource { read: false });
+        }
+
         Some(box DebugResource {
             scheme: self,
             command: String::new(),
@@ -22,3 +22,31 @@ impl Color {
     pub const RED:   Color = Color { data: 0xFFFF0000 };
     pub const WHITE: Color = Color { data: 0xFFFFFFFF };
 }
+
+/// The pixel format of window content
+#[derive(Copy, Clone, PartialEq)]
+pub enum PixelFormat {
+    /// Blue, green, red and alpha bytes, which is a `Color` in memory and the native format
+    Bgra,
+    /// Red, green, blue and alpha bytes
+    Rgba,
+}
+
+impl PixelFormat {
+    /// Parse a format from its name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bgra" => Some(PixelFormat::Bgra),
+            "rgba" => Some(PixelFormat::Rgba),
+            _ => None,
+        }
+    }
+
+    /// Get the name of the format
+    pub fn name(&self) -> &'static str {
+        match *self {
+            PixelFormat::Bgra => "bgra",
+            PixelFormat::Rgba => "rgba",
+        }
+    }
+}
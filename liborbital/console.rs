@@ -2,6 +2,19 @@ use redox::{Box, String, ToString, Vec};
 
 use super::*;
 
+/// Written to a console to read keys one at a time, without echo or line editing
+pub const RAW_MODE: &'static str = "\x1B]raw\x07";
+
+/// Written to a console to read whole lines with echo and line editing, the default
+pub const COOKED_MODE: &'static str = "\x1B]cooked\x07";
+
+/// Printed to a console to erase it and move the cursor home
+pub const CLEAR: &'static str = "\x1B[2J\x1B[H";
+
+/// Written to a console to check the keys pressed while no line was read for Ctrl-C, which
+/// sets the same interrupt flag as Ctrl-C on the kernel console
+pub const POLL_INTERRUPT: &'static str = "\x1B]interrupt\x07";
+
 /// A console char
 pub struct ConsoleChar {
     /// The char
@@ -10,6 +23,41 @@ pub struct ConsoleChar {
     color: Color,
 }
 
+/// An editing action of the console line editor
+#[derive(Copy, Clone, PartialEq)]
+pub enum EditAction {
+    /// Move to the start of the line
+    StartOfLine,
+    /// Move to the end of the line
+    EndOfLine,
+    /// Delete from the cursor to the end of the line
+    KillToEnd,
+    /// Delete the word before the cursor
+    DeleteWord,
+    /// Discard the line and pass on an interrupt
+    Interrupt,
+}
+
+/// A control key binding
+#[derive(Copy, Clone)]
+pub struct KeyBinding {
+    /// The character pressed with control
+    pub character: char,
+    /// The action to perform
+    pub action: EditAction,
+}
+
+impl KeyBinding {
+    /// The default bindings, matching readline
+    pub fn defaults() -> Vec<KeyBinding> {
+        vec![KeyBinding { character: 'a', action: EditAction::StartOfLine },
+             KeyBinding { character: 'e', action: EditAction::EndOfLine },
+             KeyBinding { character: 'k', action: EditAction::KillToEnd },
+             KeyBinding { character: 'w', action: EditAction::DeleteWord },
+             KeyBinding { character: 'c', action: EditAction::Interrupt }]
+    }
+}
+
 /// A console window
 pub struct ConsoleWindow {
     /// The window
@@ -28,6 +76,17 @@ pub struct ConsoleWindow {
     pub scroll_y: isize,
     /// Wrap the text, if true
     pub wrap: bool,
+    /// Is the control key held?
+    pub ctrl: bool,
+    /// The control key bindings
+    pub bindings: Vec<KeyBinding>,
+    /// While reading, the start of the prompt line in the output
+    /// Output printed while reading goes before it, so the prompt and input stay last
+    pub prompt_start: Option<usize>,
+    /// Are keys read one at a time, instead of as edited lines?
+    pub raw: bool,
+    /// Events taken from the window while checking for Ctrl-C, not handled yet
+    pub pending: Vec<Event>,
 }
 
 impl ConsoleWindow {
@@ -42,6 +101,11 @@ impl ConsoleWindow {
             scroll_x: 0,
             scroll_y: 0,
             wrap: true,
+            ctrl: false,
+            bindings: KeyBinding::defaults(),
+            prompt_start: None,
+            raw: false,
+            pending: Vec::new(),
         }
     }
 
@@ -55,36 +119,169 @@ impl ConsoleWindow {
                                   title).unwrap();
     }
 
-    /// Poll the window
+    /// The number of character columns
+    pub fn cols(&self) -> usize {
+        self.window.width() / 8
+    }
+
+    /// The number of character rows
+    pub fn rows(&self) -> usize {
+        self.window.height() / 16
+    }
+
+    /// Poll the window, for the events left by `poll_interrupt` first
     pub fn poll(&mut self) -> Option<Event> {
+        if !self.pending.is_empty() {
+            return Some(self.pending.remove(0));
+        }
         self.window.poll()
     }
 
-    /// Print to the window
+    /// Check the events queued on the window for Ctrl-C, without blocking
+    /// Ctrl-C is taken out and printed as `^C`, other events are kept for the next read
+    /// Keys read one at a time are left alone, Ctrl-C is one of them
+    pub fn poll_interrupt(&mut self) -> bool {
+        if self.raw {
+            return false;
+        }
+
+        let mut interrupted = false;
+        for event in self.window.poll_all() {
+            if let EventOption::Key(key_event) = event.to_option() {
+                if key_event.scancode == K_CTRL {
+                    self.ctrl = key_event.pressed;
+                } else if key_event.pressed && self.ctrl &&
+                          self.action(key_event.character) == Some(EditAction::Interrupt) {
+                    interrupted = true;
+                    continue;
+                }
+            }
+            self.pending.push(event);
+        }
+
+        if interrupted {
+            self.print("^C\n", Color::WHITE);
+            self.sync();
+        }
+        interrupted
+    }
+
+    /// The action bound to a character pressed with control
+    fn action(&self, character: char) -> Option<EditAction> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.character == character)
+            .map(|binding| binding.action)
+    }
+
+    /// Print to the window, erasing it where `CLEAR` is printed
     pub fn print(&mut self, string: &str, color: Color) {
-        for c in string.chars() {
-            self.output.push(ConsoleChar {
-                character: c,
-                color: color,
-            });
+        let mut string = string;
+        while let Some(i) = string.find(CLEAR) {
+            self.print_text(&string[.. i], color);
+            self.clear();
+            string = &string[i + CLEAR.len() ..];
         }
+        self.print_text(string, color);
     }
 
-    /// Read input
+    /// Erase the output, keeping the prompt if a line is being read
+    pub fn clear(&mut self) {
+        match self.prompt_start {
+            Some(start) => {
+                self.output = self.output.split_off(start);
+                self.prompt_start = Some(0);
+            }
+            None => self.output.clear(),
+        }
+        self.scroll_x = 0;
+        self.scroll_y = 0;
+    }
+
+    /// Print text to the window
+    fn print_text(&mut self, string: &str, color: Color) {
+        if let Some(mut start) = self.prompt_start {
+            let mut last = '\n';
+            for c in string.chars() {
+                self.output.insert(start, ConsoleChar {
+                    character: c,
+                    color: color,
+                });
+                start += 1;
+                last = c;
+            }
+            if last != '\n' {
+                self.output.insert(start, ConsoleChar {
+                    character: '\n',
+                    color: color,
+                });
+                start += 1;
+            }
+            self.prompt_start = Some(start);
+        } else {
+            for c in string.chars() {
+                self.output.push(ConsoleChar {
+                    character: c,
+                    color: color,
+                });
+            }
+        }
+    }
+
+    /// Read input, a line in cooked mode or a key in raw mode
     pub fn read(&mut self) -> Option<String> {
+        if self.raw {
+            return self.read_key();
+        }
+
+        let mut prompt_start = 0;
+        for (i, c) in self.output.iter().enumerate() {
+            if c.character == '\n' {
+                prompt_start = i + 1;
+            }
+        }
+        self.prompt_start = Some(prompt_start);
+
+        let line = self.read_line();
+
+        self.prompt_start = None;
+        line
+    }
+
+    /// Read input, handling line editing
+    fn read_line(&mut self) -> Option<String> {
         while let Some(event) = self.poll() {
             if let EventOption::Key(key_event) = event.to_option() {
-                if key_event.pressed {
+                if key_event.scancode == K_CTRL {
+                    self.ctrl = key_event.pressed;
+                } else if key_event.pressed && self.ctrl {
+                    if let Some(action) = self.action(key_event.character) {
+                        if action == EditAction::Interrupt {
+                            // Ctrl-C discards the line and is passed on as ETX
+                            self.history[self.history_i] = String::new();
+                            self.offset = 0;
+                            self.history_i = 0;
+                            self.prompt_start = None;
+                            self.print("^C\n", Color::WHITE);
+                            self.sync();
+                            return Some("\x03".to_string());
+                        }
+                        self.edit(action);
+                        self.sync();
+                    }
+                } else if key_event.pressed {
                     match key_event.scancode {
                         K_BKSP => if self.offset > 0 {
-                            self.history[self.history_i] = self.history[self.history_i][0 .. self.offset - 1].to_string() +
+                            let start = prev_char(&self.history[self.history_i], self.offset);
+                            self.history[self.history_i] = self.history[self.history_i][0 .. start].to_string() +
                                            &self.history[self.history_i][self.offset ..];
-                            self.offset -= 1;
+                            self.offset = start;
                         },
                         K_DEL => if self.offset < self.history[self.history_i].len() {
+                            let end = next_char(&self.history[self.history_i], self.offset);
                             self.history[self.history_i] =
                                 self.history[self.history_i][0 .. self.offset].to_string() +
-                                &self.history[self.history_i][self.offset + 1 .. self.history[self.history_i].len() - 1];
+                                &self.history[self.history_i][end ..];
                         },
                         K_HOME => self.offset = 0,
                         K_UP => {
@@ -94,10 +291,10 @@ impl ConsoleWindow {
                             self.offset = self.history[self.history_i].len();
                         }
                         K_LEFT => if self.offset > 0 {
-                            self.offset -= 1;
+                            self.offset = prev_char(&self.history[self.history_i], self.offset);
                         },
                         K_RIGHT => if self.offset < self.history[self.history_i].len() {
-                            self.offset += 1;
+                            self.offset = next_char(&self.history[self.history_i], self.offset);
                         },
                         K_END => self.offset = self.history[self.history_i].len(),
                         K_DOWN => {
@@ -125,7 +322,7 @@ impl ConsoleWindow {
                                 self.history[self.history_i] = self.history[self.history_i][0 .. self.offset].to_string() +
                                                &key_event.character.to_string() +
                                                &self.history[self.history_i][self.offset ..];
-                                self.offset += 1;
+                                self.offset += key_event.character.len_utf8();
                             }
                         },
                     }
@@ -137,6 +334,46 @@ impl ConsoleWindow {
         return None;
     }
 
+    /// Read a single key, without echo
+    fn read_key(&mut self) -> Option<String> {
+        while let Some(event) = self.poll() {
+            if let EventOption::Key(key_event) = event.to_option() {
+                if key_event.scancode == K_CTRL {
+                    self.ctrl = key_event.pressed;
+                } else if key_event.pressed {
+                    if let Some(key) = raw_key(&key_event, self.ctrl) {
+                        return Some(key);
+                    }
+                }
+            }
+        }
+
+        return None;
+    }
+
+    /// Apply an editing action to the current line
+    pub fn edit(&mut self, action: EditAction) {
+        let line = self.history[self.history_i].clone();
+        match action {
+            EditAction::StartOfLine => self.offset = 0,
+            EditAction::EndOfLine => self.offset = line.len(),
+            EditAction::KillToEnd => self.history[self.history_i] = line[.. self.offset].to_string(),
+            EditAction::DeleteWord => {
+                let bytes = line.as_bytes();
+                let mut start = self.offset;
+                while start > 0 && bytes[start - 1] == b' ' {
+                    start -= 1;
+                }
+                while start > 0 && bytes[start - 1] != b' ' {
+                    start -= 1;
+                }
+                self.history[self.history_i] = line[.. start].to_string() + &line[self.offset ..];
+                self.offset = start;
+            }
+            EditAction::Interrupt => (),
+        }
+    }
+
     /// Redraw the window
     pub fn sync(&mut self) {
         let scroll_x = self.scroll_x;
@@ -202,7 +439,7 @@ impl ConsoleWindow {
                     col += 1;
                 }
 
-                i += 1;
+                i += c.len_utf8();
             }
 
             if self.wrap && col >= cols {
@@ -224,3 +461,81 @@ impl ConsoleWindow {
         }
     }
 }
+
+/// The byte offset of the character before `offset` in `line`
+fn prev_char(line: &str, offset: usize) -> usize {
+    line[.. offset].char_indices().last().map_or(0, |(i, _)| i)
+}
+
+/// The byte offset of the character after `offset` in `line`
+fn next_char(line: &str, offset: usize) -> usize {
+    line[offset ..].chars().next().map_or(offset, |c| offset + c.len_utf8())
+}
+
+/// The input for a key in raw mode, as a character, a control character, or an escape
+/// sequence for keys without a character
+fn raw_key(key_event: &KeyEvent, ctrl: bool) -> Option<String> {
+    let sequence = match key_event.scancode {
+        K_UP => "\x1B[A",
+        K_DOWN => "\x1B[B",
+        K_RIGHT => "\x1B[C",
+        K_LEFT => "\x1B[D",
+        K_HOME => "\x1B[H",
+        K_END => "\x1B[F",
+        K_DEL => "\x1B[3~",
+        K_BKSP => "\x7F",
+        _ => "",
+    };
+
+    if !sequence.is_empty() {
+        Some(sequence.to_string())
+    } else if key_event.character == '\x00' {
+        None
+    } else if ctrl && key_event.character >= 'a' && key_event.character <= 'z' {
+        Some(((key_event.character as u8 & 0x1F) as char).to_string())
+    } else {
+        Some(key_event.character.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{next_char, prev_char, raw_key};
+    use super::super::{KeyEvent, K_CTRL, K_LEFT, K_UP};
+
+    fn key(character: char, scancode: u8) -> KeyEvent {
+        KeyEvent {
+            character: character,
+            scancode: scancode,
+            pressed: true,
+        }
+    }
+
+    #[test]
+    fn raw_keys() {
+        assert_eq!(raw_key(&key('q', 0x10), false).unwrap(), "q");
+        assert_eq!(raw_key(&key('é', 0), false).unwrap(), "é");
+        assert_eq!(raw_key(&key('c', 0x2E), true).unwrap(), "\x03");
+        assert_eq!(raw_key(&key('\x00', K_UP), false).unwrap(), "\x1B[A");
+        assert_eq!(raw_key(&key('\x00', K_LEFT), false).unwrap(), "\x1B[D");
+        assert!(raw_key(&key('\x00', K_CTRL), false).is_none());
+    }
+
+    #[test]
+    fn char_steps() {
+        let line = "aé€😀b";
+        assert_eq!(next_char(line, 0), 1);
+        assert_eq!(next_char(line, 1), 3);
+        assert_eq!(next_char(line, 3), 6);
+        assert_eq!(next_char(line, 6), 10);
+        assert_eq!(next_char(line, 10), 11);
+        assert_eq!(next_char(line, 11), 11);
+
+        assert_eq!(prev_char(line, 11), 10);
+        assert_eq!(prev_char(line, 10), 6);
+        assert_eq!(prev_char(line, 6), 3);
+        assert_eq!(prev_char(line, 3), 1);
+        assert_eq!(prev_char(line, 1), 0);
+        assert_eq!(prev_char(line, 0), 0);
+    }
+}
@@ -1,5 +1,7 @@
 use core::char;
 
+use redox::time::Clock;
+
 /// An optional event
 #[derive(Copy, Clone)]
 pub enum EventOption {
@@ -9,12 +11,37 @@ pub enum EventOption {
     Key(KeyEvent),
     /// A quit request event
     Quit(QuitEvent),
+    /// A resize event
+    Resize(ResizeEvent),
+    /// A timer tick
+    Tick(TickEvent),
     /// An unknown event
     Unknown(Event),
     /// No event
     None,
 }
 
+/// The size of an `Event` on the wire
+///
+/// The layout is the code as a little endian `u32`, followed by `a`, `b` and `c` as little
+/// endian `i64`s, so that it does not depend on the in-memory layout of `Event`
+pub const EVENT_SIZE: usize = 28;
+
+/// Mouse events, for an event mask
+pub const EVENT_MOUSE: usize = 1;
+/// Key events, for an event mask
+pub const EVENT_KEY: usize = 2;
+/// Quit events, for an event mask
+pub const EVENT_QUIT: usize = 4;
+/// Resize events, for an event mask
+pub const EVENT_RESIZE: usize = 8;
+/// All events, for an event mask
+pub const EVENT_ALL: usize = EVENT_MOUSE | EVENT_KEY | EVENT_QUIT | EVENT_RESIZE;
+/// Timer ticks, for an event mask
+///
+/// Ticks are not part of `EVENT_ALL`, a window only gets them if it asks for them
+pub const EVENT_TICK: usize = 16;
+
 /// An event
 #[derive(Copy, Clone)]
 #[repr(packed)]
@@ -36,6 +63,52 @@ impl Event {
         }
     }
 
+    /// Serialize the event into its wire layout
+    pub fn to_bytes(&self) -> [u8; EVENT_SIZE] {
+        let mut bytes = [0; EVENT_SIZE];
+
+        let code = self.code as u32;
+        for i in 0..4 {
+            bytes[i] = (code >> (i * 8)) as u8;
+        }
+
+        let fields = [self.a as i64, self.b as i64, self.c as i64];
+        for (field_i, field) in fields.iter().enumerate() {
+            for i in 0..8 {
+                bytes[4 + field_i * 8 + i] = (*field >> (i * 8)) as u8;
+            }
+        }
+
+        bytes
+    }
+
+    /// Deserialize an event from its wire layout
+    /// Returns `None` if there are not enough bytes, or the code is not a valid char
+    pub fn from_bytes(bytes: &[u8]) -> Option<Event> {
+        if bytes.len() < EVENT_SIZE {
+            return None;
+        }
+
+        let mut code = 0;
+        for i in 0..4 {
+            code |= (bytes[i] as u32) << (i * 8);
+        }
+
+        let mut fields = [0i64; 3];
+        for (field_i, field) in fields.iter_mut().enumerate() {
+            for i in 0..8 {
+                *field |= (bytes[4 + field_i * 8 + i] as i64) << (i * 8);
+            }
+        }
+
+        char::from_u32(code).map(|code| Event {
+            code: code,
+            a: fields[0] as isize,
+            b: fields[1] as isize,
+            c: fields[2] as isize,
+        })
+    }
+
     /// Convert the event ot an optional event
     // TODO: Consider doing this via a From trait.
     pub fn to_option(self) -> EventOption {
@@ -43,6 +116,8 @@ impl Event {
             'm' => EventOption::Mouse(MouseEvent::from_event(self)),
             'k' => EventOption::Key(KeyEvent::from_event(self)),
             'q' => EventOption::Quit(QuitEvent::from_event(self)),
+            'r' => EventOption::Resize(ResizeEvent::from_event(self)),
+            't' => EventOption::Tick(TickEvent::from_event(self)),
             '\0' => EventOption::None,
             _ => EventOption::Unknown(self),
         }
@@ -87,6 +162,38 @@ impl MouseEvent {
     }
 }
 
+/// The most milliseconds between the two clicks of a double click
+pub const DOUBLE_CLICK_MS: u64 = 500;
+
+/// Recognizes double clicks, two clicks at the same place in quick succession
+pub struct DoubleClick {
+    /// The time and place of the last click, unless it ended a double click
+    last: Option<(u64, isize, isize)>,
+}
+
+impl DoubleClick {
+    /// Create a recognizer that has seen no clicks
+    pub fn new() -> Self {
+        DoubleClick { last: None }
+    }
+
+    /// Record a click at the time on `clock`, returning true if it ends a double click
+    pub fn click(&mut self, x: isize, y: isize, clock: &Clock) -> bool {
+        let now = clock.monotonic_ms();
+        let double = match self.last {
+            Some((time, last_x, last_y)) => now - time < DOUBLE_CLICK_MS && x == last_x && y == last_y,
+            None => false,
+        };
+
+        self.last = if double {
+            None
+        } else {
+            Some((now, x, y))
+        };
+        double
+    }
+}
+
 /// Escape key
 pub const K_ESC: u8 = 0x01;
 /// Backspace key
@@ -193,3 +300,84 @@ impl QuitEvent {
         QuitEvent
     }
 }
+
+/// A event telling the window its new size
+#[derive(Copy, Clone)]
+pub struct ResizeEvent {
+    /// The new width
+    pub width: usize,
+    /// The new height
+    pub height: usize,
+}
+
+impl ResizeEvent {
+    /// Convert to an `Event`
+    pub fn to_event(&self) -> Event {
+        Event {
+            code: 'r',
+            a: self.width as isize,
+            b: self.height as isize,
+            c: 0,
+        }
+    }
+
+    /// Convert from an `Event`
+    pub fn from_event(event: Event) -> ResizeEvent {
+        ResizeEvent {
+            width: event.a as usize,
+            height: event.b as usize,
+        }
+    }
+}
+
+/// A timer tick, sent at the interval set in the display settings
+#[derive(Copy, Clone)]
+pub struct TickEvent {
+    /// The monotonic time of the tick, in milliseconds
+    pub time_ms: u64,
+}
+
+impl TickEvent {
+    /// Convert to an `Event`
+    pub fn to_event(&self) -> Event {
+        Event {
+            code: 't',
+            a: self.time_ms as isize,
+            b: 0,
+            c: 0,
+        }
+    }
+
+    /// Convert from an `Event`
+    pub fn from_event(event: Event) -> TickEvent {
+        TickEvent {
+            time_ms: event.a as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use redox::time::FakeClock;
+
+    use super::*;
+
+    #[test]
+    fn double_click() {
+        let clock = FakeClock::new();
+        let mut clicks = DoubleClick::new();
+        assert!(!clicks.click(1, 2, &clock));
+        clock.advance(DOUBLE_CLICK_MS - 1);
+        assert!(clicks.click(1, 2, &clock));
+
+        // A third click starts over
+        assert!(!clicks.click(1, 2, &clock));
+
+        clock.advance(DOUBLE_CLICK_MS);
+        assert!(!clicks.click(1, 2, &clock));
+        clock.advance(100);
+        assert!(!clicks.click(3, 2, &clock));
+        clock.advance(100);
+        assert!(clicks.click(3, 2, &clock));
+    }
+}
@@ -8,7 +8,7 @@
 extern crate redox;
 
 pub use bmp::BmpFile;
-pub use color::Color;
+pub use color::{Color, PixelFormat};
 pub use event::*;
 pub use point::Point;
 pub use size::Size;
@@ -1,8 +1,9 @@
 use redox::Box;
+use redox::cmp;
 use redox::fs::File;
 use redox::io::*;
 use redox::mem;
-use redox::ops::DerefMut;
+use redox::rc::Rc;
 use redox::slice;
 use redox::syscall::sys_yield;
 use redox::String;
@@ -10,9 +11,77 @@ use redox::ToString;
 use redox::to_num::ToNum;
 use redox::Vec;
 
-use super::Event;
+use super::{Event, EventOption, EVENT_SIZE};
 use super::Color;
 
+/// The resource behind a window, which receives the content and produces the events
+///
+/// This is a `File` on the orbital scheme, but can be replaced to test windows without a
+/// running compositor
+pub trait WindowResource {
+    /// Read events
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize>;
+    /// Write content
+    fn write(&mut self, buf: &[u8]) -> Option<usize>;
+    /// Seek in the content
+    fn seek(&mut self, pos: SeekFrom) -> Option<usize>;
+    /// Flip the content
+    fn sync(&mut self) -> bool;
+    /// Get the path, which holds the dimensions
+    fn path(&self) -> Option<String>;
+}
+
+impl WindowResource for File {
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        Read::read(self, buf)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        Write::write(self, buf)
+    }
+
+    fn seek(&mut self, pos: SeekFrom) -> Option<usize> {
+        Seek::seek(self, pos)
+    }
+
+    fn sync(&mut self) -> bool {
+        File::sync(self)
+    }
+
+    fn path(&self) -> Option<String> {
+        File::path(self)
+    }
+}
+
+/// Writes to a window that start with this are control settings, not content
+///
+/// The content is written whole from the start, so only a frame whose first pixels spell this
+/// prefix would be mistaken for settings
+pub const CONTROL_PREFIX: &'static [u8] = b"\x1B]orbital;";
+
+/// The font, loaded by the first window of the process and shared by all of its windows
+static mut font_cache: *mut Rc<Vec<u8>> = 0 as *mut Rc<Vec<u8>>;
+
+/// Get the font, only reading the font file once per process
+/// This leaves the orbital resource as the only scheme access when creating a window
+/// A missing font is reported once, and gives an empty font
+fn load_font() -> Rc<Vec<u8>> {
+    unsafe {
+        if font_cache as usize == 0 {
+            let mut font = Vec::new();
+            if let Some(mut font_file) = File::open("file:/ui/unifont.font") {
+                font_file.read_to_end(&mut font);
+            }
+            if font.is_empty() {
+                debugln!("Failed to read font file:/ui/unifont.font, text will be drawn as boxes");
+            }
+            font_cache = Box::into_raw(box Rc::new(font));
+        }
+
+        (*font_cache).clone()
+    }
+}
+
 /// A window
 pub struct Window {
     /// The x coordinate of the window
@@ -26,36 +95,52 @@ pub struct Window {
     /// The title of the window
     t: String,
     /// The input scheme
-    file: File,
-    /// Font file
-    font: Vec<u8>,
+    file: Box<WindowResource>,
+    /// Font file, shared with the other windows of the process
+    font: Rc<Vec<u8>>,
     /// Window data
     data: Vec<u32>,
+    /// The data as of the last sync, if unchanged syncs are skipped
+    synced: Option<Vec<u32>>,
 }
 
 impl Window {
     /// Create a new window
+    /// The window opens on top of the others and takes the focus
     pub fn new(x: isize, y: isize, w: usize, h: usize, title: &str) -> Option<Box<Self>> {
-        let mut font = Vec::new();
-        if let Some(mut font_file) = File::open("file:/ui/unifont.font") {
-            font_file.read_to_end(&mut font);
-        }
-
-        match File::open(&format!("orbital:///{}/{}/{}/{}/{}", x, y, w, h, title)) {
-            Some(file) => Some(box Window {
-                x: x,
-                y: y,
-                w: w,
-                h: h,
-                t: title.to_string(),
-                file: file,
-                font: font,
-                data: vec![0; w * h * 4],
-            }),
+        Window::open("", x, y, w, h, title)
+    }
+
+    /// Create a new window below the others, without taking the focus
+    pub fn new_background(x: isize, y: isize, w: usize, h: usize, title: &str) -> Option<Box<Self>> {
+        Window::open("+background", x, y, w, h, title)
+    }
+
+    fn open(host: &str, x: isize, y: isize, w: usize, h: usize, title: &str) -> Option<Box<Self>> {
+        let font = load_font();
+
+        match File::open(&format!("orbital://{}/{}/{}/{}/{}/{}", host, x, y, w, h, title)) {
+            Some(file) => Some(Window::from_resource(x, y, w, h, title, box file, font)),
             None => None
         }
     }
 
+    /// Create a window on top of any `WindowResource`, with the given font
+    pub fn from_resource(x: isize, y: isize, w: usize, h: usize, title: &str,
+                         resource: Box<WindowResource>, font: Rc<Vec<u8>>) -> Box<Self> {
+        box Window {
+            x: x,
+            y: y,
+            w: w,
+            h: h,
+            t: title.to_string(),
+            file: resource,
+            font: font,
+            data: vec![0; w * h],
+            synced: None,
+        }
+    }
+
     //TODO: Replace with smarter mechanism, maybe a move event?
     pub fn sync_path(&mut self) {
         if let Some(path) = self.file.path() {
@@ -67,13 +152,34 @@ impl Window {
             if let Some(y) = parts.get(4) {
                 self.y = y.to_num_signed();
             }
-            if let Some(w) = parts.get(5) {
-                self.w = w.to_num();
-            }
-            if let Some(h) = parts.get(6) {
-                self.h = h.to_num();
+            let w = match parts.get(5) {
+                Some(w) => w.to_num(),
+                None => self.w,
+            };
+            let h = match parts.get(6) {
+                Some(h) => h.to_num(),
+                None => self.h,
+            };
+            self.resize(w, h);
+        }
+    }
+
+    /// Resize the window buffer, keeping the content that still fits
+    pub fn resize(&mut self, w: usize, h: usize) {
+        if w == self.w && h == self.h {
+            return;
+        }
+
+        let mut data = vec![0; w * h];
+        for y in 0..cmp::min(h, self.h) {
+            for x in 0..cmp::min(w, self.w) {
+                data[y * w + x] = self.data[y * self.w + x];
             }
         }
+
+        self.w = w;
+        self.h = h;
+        self.data = data;
     }
 
     /// Get x
@@ -108,6 +214,44 @@ impl Window {
         //TODO
     }
 
+    /// Only receive the events in `mask`, a combination of `EVENT_MOUSE`, `EVENT_KEY`, `EVENT_QUIT`,
+    /// `EVENT_RESIZE` and `EVENT_TICK`
+    pub fn set_event_mask(&mut self, mask: usize) -> bool {
+        self.control(format!("events={}", mask).as_bytes())
+    }
+
+    /// Set the icon shown in the title bar and the task bar
+    pub fn set_icon(&mut self, w: usize, h: usize, data: &[Color]) -> bool {
+        if data.len() != w * h {
+            return false;
+        }
+
+        //The icon pixels follow its header line
+        let mut vec: Vec<u8> = format!("icon={}/{}\n", w, h).into_bytes();
+        for color in data.iter() {
+            let bytes: [u8; 4] = unsafe { mem::transmute(color.data) };
+            vec.push_all(&bytes);
+        }
+
+        self.control(&vec)
+    }
+
+    /// Show the pixels that are exactly `key` as transparent, or no pixels with `None`
+    pub fn set_color_key(&mut self, key: Option<Color>) -> bool {
+        let line = match key {
+            Some(color) => format!("color_key={:08X}", color.data),
+            None => "color_key=".to_string(),
+        };
+        self.control(line.as_bytes())
+    }
+
+    /// Write control settings, behind `CONTROL_PREFIX`
+    fn control(&mut self, settings: &[u8]) -> bool {
+        let mut vec = CONTROL_PREFIX.to_vec();
+        vec.push_all(settings);
+        self.file.write(&vec).is_some()
+    }
+
     /// Draw a pixel
     pub fn pixel(&mut self, x: isize, y: isize, color: Color) {
         if x >= 0 && y >= 0 && x < self.w as isize && y < self.h as isize {
@@ -117,7 +261,19 @@ impl Window {
     }
 
     /// Draw a character, using the loaded font
+    ///
+    /// Without a font, every visible character is drawn as a box
     pub fn char(&mut self, x: isize, y: isize, c: char, color: Color) {
+        if self.font.is_empty() {
+            if c > ' ' {
+                self.rect(x + 1, y + 2, 6, 1, color);
+                self.rect(x + 1, y + 13, 6, 1, color);
+                self.rect(x + 1, y + 3, 1, 10, color);
+                self.rect(x + 6, y + 3, 1, 10, color);
+            }
+            return;
+        }
+
         let mut offset = (c as usize) * 16;
         for row in 0..16 {
             let row_data;
@@ -137,6 +293,46 @@ impl Window {
         }
     }
 
+    /// Draw a decimal integer, using the loaded font
+    pub fn draw_int(&mut self, x: isize, y: isize, value: i64, color: Color) {
+        let mut x = x;
+        if value < 0 {
+            self.char(x, y, '-', color);
+            x += 8;
+        }
+        // The magnitude of i64::MIN does not fit in an i64
+        let magnitude = if value < 0 {
+            (!(value as u64)).wrapping_add(1)
+        } else {
+            value as u64
+        };
+        self.draw_digits(x, y, magnitude, 10, color);
+    }
+
+    /// Draw an unsigned integer in hexadecimal, using the loaded font
+    pub fn draw_hex(&mut self, x: isize, y: isize, value: u64, color: Color) {
+        self.draw_digits(x, y, value, 16, color);
+    }
+
+    /// Draw the digits of a number in a radix up to 16
+    fn draw_digits(&mut self, x: isize, y: isize, value: u64, radix: u64, color: Color) {
+        let mut digits = [0u8; 64];
+        let mut len = 0;
+        let mut value = value;
+        loop {
+            digits[len] = b"0123456789ABCDEF"[(value % radix) as usize];
+            len += 1;
+            value /= radix;
+            if value == 0 {
+                break;
+            }
+        }
+
+        for i in 0..len {
+            self.char(x + 8 * i as isize, y, digits[len - 1 - i] as char, color);
+        }
+    }
+
     //TODO move, resize, set_title
 
     /// Set entire window to a color
@@ -174,29 +370,87 @@ impl Window {
     }
 
     /// Poll for an event
-    //TODO: clean this up
     pub fn poll(&mut self) -> Option<Event> {
-        let mut event = box Event::new();
-        let event_ptr: *mut Event = event.deref_mut();
+        let mut bytes = [0; EVENT_SIZE];
         loop {
-            match self.file.read(&mut unsafe {
-                slice::from_raw_parts_mut(event_ptr as *mut u8, mem::size_of::<Event>())
-            }) {
+            match self.file.read(&mut bytes) {
                 Some(0) => unsafe { sys_yield() },
-                Some(_) => return Some(*event),
+                Some(_) => return Event::from_bytes(&bytes),
                 None => return None,
             }
         }
     }
 
+    /// Return every event that is currently queued, without blocking
+    pub fn poll_all(&mut self) -> Vec<Event> {
+        let mut events = Vec::new();
+        let mut bytes = [0; EVENT_SIZE];
+        loop {
+            match self.file.read(&mut bytes) {
+                Some(0) | None => break,
+                Some(_) => if let Some(event) = Event::from_bytes(&bytes) {
+                    events.push(event);
+                },
+            }
+        }
+        events
+    }
+
+    /// Skip syncs when nothing was drawn since the last one
+    ///
+    /// This keeps a copy of the data, and compares against it on every sync. That costs a pass
+    /// over the buffer, so apps that draw something new every frame should leave it off.
+    pub fn set_skip_unchanged(&mut self, skip: bool) {
+        self.synced = if skip {
+            Some(Vec::new())
+        } else {
+            None
+        };
+    }
+
     /// Flip the window buffer
+    /// The buffer is also flipped when the window is dropped, but syncing explicitly after
+    /// drawing is still recommended, as the window can only report a failure here
     pub fn sync(&mut self) -> bool {
+        if let Some(ref mut synced) = self.synced {
+            if *synced == self.data {
+                return true;
+            }
+            *synced = self.data.clone();
+        }
+
         self.file.seek(SeekFrom::Start(0));
-        let to_write: &[u8] = unsafe{ mem::transmute::<&[u32],&[u8]>(&self.data) };
+        let to_write: &[u8] = unsafe {
+            slice::from_raw_parts(self.data.as_ptr() as *const u8, self.data.len() * 4)
+        };
         self.file.write(to_write);
         return self.file.sync();
     }
 
+    /// Handle events until `handler` returns false
+    ///
+    /// On a resize event, the buffer is resized and the newly exposed area is filled with
+    /// `background` before `handler` sees the event, so it can redraw at the new size
+    pub fn run<F: FnMut(&mut Window, Event) -> bool>(&mut self, background: Color, mut handler: F) {
+        while let Some(event) = self.poll() {
+            if let EventOption::Resize(resize_event) = event.to_option() {
+                let (old_w, old_h) = (self.w, self.h);
+                self.resize(resize_event.width, resize_event.height);
+                for y in 0..self.h {
+                    for x in 0..self.w {
+                        if x >= old_w || y >= old_h {
+                            self.data[y * self.w + x] = background.data;
+                        }
+                    }
+                }
+            }
+
+            if !handler(self, event) {
+                break;
+            }
+        }
+    }
+
     /// Return a iterator over events
     pub fn event_iter<'a>(&'a mut self) -> EventIter<'a> {
         EventIter {
@@ -205,6 +459,16 @@ impl Window {
     }
 }
 
+impl Drop for Window {
+    /// Flip the last frame before the resource is closed, so drawing after the last sync
+    /// is not lost
+    fn drop(&mut self) {
+        if !self.sync() {
+            debugln!("Failed to flush window {} when closing it", self.t);
+        }
+    }
+}
+
 /// Event iterator
 pub struct EventIter<'a> {
     window: &'a mut Window,
@@ -216,3 +480,232 @@ impl<'a> Iterator for EventIter<'a> {
         self.window.poll()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use redox::{String, ToString, Vec};
+    use redox::cell::{Cell, RefCell};
+    use redox::io::SeekFrom;
+    use redox::rc::Rc;
+
+    use super::*;
+    use super::super::{Color, Event, ResizeEvent, QuitEvent, EVENT_SIZE};
+
+    /// A resource that keeps everything in memory
+    #[derive(Default)]
+    struct MemoryResource {
+        content: Rc<RefCell<Vec<u8>>>,
+        seek: usize,
+        events: Vec<Event>,
+        syncs: Rc<Cell<usize>>,
+    }
+
+    impl WindowResource for MemoryResource {
+        fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+            if self.events.is_empty() {
+                return None;
+            }
+
+            let bytes = self.events.remove(0).to_bytes();
+            for (b, byte) in buf.iter_mut().zip(bytes.iter()) {
+                *b = *byte;
+            }
+            Some(EVENT_SIZE)
+        }
+
+        fn write(&mut self, buf: &[u8]) -> Option<usize> {
+            let mut content = self.content.borrow_mut();
+            for b in buf.iter() {
+                if self.seek < content.len() {
+                    content[self.seek] = *b;
+                } else {
+                    content.push(*b);
+                }
+                self.seek += 1;
+            }
+            Some(buf.len())
+        }
+
+        fn seek(&mut self, pos: SeekFrom) -> Option<usize> {
+            if let SeekFrom::Start(offset) = pos {
+                self.seek = offset;
+            }
+            Some(self.seek)
+        }
+
+        fn sync(&mut self) -> bool {
+            self.syncs.set(self.syncs.get() + 1);
+            true
+        }
+
+        fn path(&self) -> Option<String> {
+            Some("orbital:///0/0/4/2/test".to_string())
+        }
+    }
+
+    #[test]
+    fn pixel_bounds() {
+        let resource = MemoryResource::default();
+        let mut window = Window::from_resource(0, 0, 4, 4, "test", box resource, Rc::new(Vec::new()));
+        window.pixel(3, 3, Color::WHITE);
+        window.pixel(-1, 0, Color::WHITE);
+        window.pixel(0, -1, Color::WHITE);
+        window.pixel(4, 0, Color::WHITE);
+        window.pixel(0, 4, Color::WHITE);
+        assert_eq!(window.data.len(), 16);
+        assert_eq!(window.data[15], Color::WHITE.data);
+        assert_eq!(window.data.iter().filter(|&&pixel| pixel != 0).count(), 1);
+    }
+
+    #[test]
+    fn rect_clipping() {
+        let resource = MemoryResource::default();
+        let mut window = Window::from_resource(0, 0, 4, 4, "test", box resource, Rc::new(Vec::new()));
+        window.rect(2, 2, 8, 8, Color::RED);
+        assert_eq!(window.data.iter().filter(|&&pixel| pixel == Color::RED.data).count(), 4);
+
+        window.set(Color::BLUE);
+        assert!(window.data.iter().all(|&pixel| pixel == Color::BLUE.data));
+    }
+
+    #[test]
+    fn char_placement() {
+        let mut font = vec![0; 'A' as usize * 16 + 16];
+        font['A' as usize * 16] = 0b1000_0001;
+        let resource = MemoryResource::default();
+        let mut window = Window::from_resource(0, 0, 16, 16, "test", box resource, Rc::new(font));
+        window.char(4, 2, 'A', Color::GREEN);
+        assert_eq!(window.data[2 * 16 + 4], Color::GREEN.data);
+        assert_eq!(window.data[2 * 16 + 11], Color::GREEN.data);
+        assert_eq!(window.data.iter().filter(|&&pixel| pixel != 0).count(), 2);
+    }
+
+    #[test]
+    fn draw_int_signs() {
+        // Every glyph is a single pixel in its top left corner
+        let mut font = vec![0; 128 * 16];
+        for c in 0..128 {
+            font[c * 16] = 0b1000_0000;
+        }
+
+        let resource = MemoryResource::default();
+        let mut window = Window::from_resource(0, 0, 64, 16, "test", box resource, Rc::new(font.clone()));
+        window.draw_int(0, 0, 0, Color::WHITE);
+        assert_eq!(window.data.iter().filter(|&&pixel| pixel != 0).count(), 1);
+
+        let resource = MemoryResource::default();
+        let mut window = Window::from_resource(0, 0, 64, 16, "test", box resource, Rc::new(font.clone()));
+        window.draw_int(0, 0, -42, Color::WHITE);
+        assert_eq!(window.data.iter().filter(|&&pixel| pixel != 0).count(), 3);
+
+        let resource = MemoryResource::default();
+        let mut window = Window::from_resource(0, 0, 256, 16, "test", box resource, Rc::new(font));
+        window.draw_hex(0, 0, 0xFF, Color::WHITE);
+        assert_eq!(window.data.iter().filter(|&&pixel| pixel != 0).count(), 2);
+    }
+
+    #[test]
+    fn resize_buffer() {
+        let resource = MemoryResource::default();
+        let mut window = Window::from_resource(0, 0, 2, 2, "test", box resource, Rc::new(Vec::new()));
+        window.pixel(1, 1, Color::WHITE);
+        window.resize(3, 1);
+        assert_eq!(window.data.len(), 3);
+        assert!(window.data.iter().all(|&pixel| pixel == 0));
+
+        window.resize(4, 2);
+        window.pixel(3, 1, Color::WHITE);
+        window.sync_path();
+        assert_eq!(window.width(), 4);
+        assert_eq!(window.height(), 2);
+        assert_eq!(window.data[7], Color::WHITE.data);
+    }
+
+    #[test]
+    fn sync_whole_frame() {
+        let content = Rc::new(RefCell::new(Vec::new()));
+        let resource = MemoryResource { content: content.clone(), ..Default::default() };
+        let mut window = Window::from_resource(0, 0, 3, 2, "test", box resource, Rc::new(Vec::new()));
+        window.pixel(2, 1, Color::WHITE);
+        assert!(window.sync());
+
+        let content = content.borrow();
+        assert_eq!(content.len(), 3 * 2 * 4);
+        assert!(content[.. 20].iter().all(|&b| b == 0));
+        assert!(content[20 ..].iter().all(|&b| b != 0));
+    }
+
+    #[test]
+    fn control_prefix() {
+        let content = Rc::new(RefCell::new(Vec::new()));
+        let resource = MemoryResource { content: content.clone(), ..Default::default() };
+        let mut window = Window::from_resource(0, 0, 2, 2, "test", box resource, Rc::new(Vec::new()));
+        assert!(window.set_event_mask(3));
+
+        let content = content.borrow();
+        assert!(content.starts_with(CONTROL_PREFIX));
+        assert_eq!(&content[CONTROL_PREFIX.len() ..], b"events=3");
+    }
+
+    #[test]
+    fn run_resize() {
+        let events = vec![ResizeEvent { width: 3, height: 2 }.to_event(), QuitEvent.to_event()];
+        let resource = MemoryResource { events: events, ..Default::default() };
+        let mut window = Window::from_resource(0, 0, 2, 1, "test", box resource, Rc::new(Vec::new()));
+        window.set(Color::RED);
+
+        let mut sizes = Vec::new();
+        window.run(Color::BLUE, |window, event| {
+            sizes.push((window.width(), window.height()));
+            event.code != 'q'
+        });
+
+        assert_eq!(sizes, vec![(3, 2), (3, 2)]);
+        assert_eq!(window.data, vec![Color::RED.data, Color::RED.data, Color::BLUE.data,
+                                     Color::BLUE.data, Color::BLUE.data, Color::BLUE.data]);
+    }
+
+    #[test]
+    fn skip_unchanged_sync() {
+        let syncs = Rc::new(Cell::new(0));
+        let resource = MemoryResource { syncs: syncs.clone(), ..Default::default() };
+        let mut window = Window::from_resource(0, 0, 2, 2, "test", box resource, Rc::new(Vec::new()));
+        window.sync();
+        window.sync();
+        assert_eq!(syncs.get(), 2);
+
+        window.set_skip_unchanged(true);
+        window.sync();
+        window.sync();
+        assert_eq!(syncs.get(), 3);
+
+        window.pixel(0, 0, Color::WHITE);
+        window.sync();
+        assert_eq!(syncs.get(), 4);
+    }
+
+    #[test]
+    fn drop_flushes() {
+        let syncs = Rc::new(Cell::new(0));
+        {
+            let resource = MemoryResource { syncs: syncs.clone(), ..Default::default() };
+            let mut window = Window::from_resource(0, 0, 2, 2, "test", box resource, Rc::new(Vec::new()));
+            window.pixel(0, 0, Color::WHITE);
+        }
+        assert_eq!(syncs.get(), 1);
+    }
+
+    #[test]
+    fn missing_font_boxes() {
+        let resource = MemoryResource::default();
+        let mut window = Window::from_resource(0, 0, 8, 16, "test", box resource, Rc::new(Vec::new()));
+        window.char(0, 0, ' ', Color::WHITE);
+        assert!(window.data.iter().all(|&pixel| pixel == 0));
+
+        window.char(0, 0, 'A', Color::WHITE);
+        assert_eq!(window.data[2 * 8 + 1], Color::WHITE.data);
+        assert_eq!(window.data[13 * 8 + 6], Color::WHITE.data);
+        assert_eq!(window.data[8 * 8 + 3], 0);
+        assert_eq!(window.data.iter().filter(|&&pixel| pixel != 0).count(), 32);
+    }
+}
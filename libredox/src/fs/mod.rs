@@ -5,7 +5,7 @@ use str;
 use string::{String, ToString};
 use vec::Vec;
 
-use syscall::{sys_open, sys_dup, sys_close, sys_execve, sys_fpath, sys_ftruncate, sys_read, sys_write, sys_lseek, sys_fsync, sys_chdir, sys_mkdir};
+use syscall::{sys_open, sys_dup, sys_close, sys_execve, sys_fpath, sys_ftruncate, sys_read, sys_write, sys_lseek, sys_fsync, sys_chdir, sys_mkdir, sys_unlink};
 use syscall::common::{O_RDWR, O_CREAT, O_TRUNC, SEEK_SET, SEEK_CUR, SEEK_END};
 
 /// A Unix-style file
@@ -225,6 +225,22 @@ pub fn read_dir(path: &str) -> Option<ReadDir> {
     }
 }
 
+/// Remove a file, returning false if it could not be removed
+pub fn remove_file(path: &str) -> bool {
+    unsafe { sys_unlink((path.to_string() + "\0").as_ptr()) == 0 }
+}
+
+/// Remove an empty directory, returning false if it could not be removed
+/// The directory is named with a trailing slash, as when it is read
+pub fn remove_dir(path: &str) -> bool {
+    let path = if path.ends_with('/') {
+        path.to_string()
+    } else {
+        path.to_string() + "/"
+    };
+    remove_file(&path)
+}
+
 pub fn change_cwd(path: &str) -> bool {
     let file_option = if path.is_empty() || path.ends_with('/') {
         File::open(path)
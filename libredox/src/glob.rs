@@ -0,0 +1,164 @@
+//! Wildcard matching, as used by shell globs and package `accept` patterns
+
+use vec::Vec;
+
+/// Does `text` match the wildcard `pattern`?
+///
+/// `*` matches any run of characters, `?` matches one character, and `[abc]`, `[a-z]` or
+/// `[!abc]` match one character from, or not from, a class. A `[` without a closing `]`
+/// matches itself.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let mut p = 0;
+    let mut t = 0;
+    // The position after the last `*` and the text position it is matched up to
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        let step = match pattern.get(p) {
+            Some(&'*') => {
+                star = Some((p + 1, t));
+                p += 1;
+                continue;
+            }
+            Some(&'?') => Some(1),
+            Some(&'[') => match class_match(&pattern[p ..], text[t]) {
+                Some((true, len)) => Some(len),
+                Some((false, _)) => None,
+                None => if text[t] == '[' { Some(1) } else { None },
+            },
+            Some(&c) => if c == text[t] { Some(1) } else { None },
+            None => None,
+        };
+
+        match step {
+            Some(len) => {
+                p += len;
+                t += 1;
+            }
+            None => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p ..].iter().all(|&c| c == '*')
+}
+
+/// Does `pattern` contain any wildcards?
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains('*') || pattern.contains('?') || pattern.contains('[')
+}
+
+/// Match `c` against the class at the start of `pattern`
+/// Returns whether it matched and the length of the class, or `None` if the class is not closed
+fn class_match(pattern: &[char], c: char) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negated = match pattern.get(i) {
+        Some(&'!') | Some(&'^') => {
+            i += 1;
+            true
+        }
+        _ => false,
+    };
+
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        match pattern.get(i) {
+            Some(&']') if !first => return Some((matched != negated, i + 1)),
+            Some(&start) => {
+                if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).map_or(false, |&end| end != ']') {
+                    let end = pattern[i + 2];
+                    if c >= start && c <= end {
+                        matched = true;
+                    }
+                    i += 3;
+                } else {
+                    if c == start {
+                        matched = true;
+                    }
+                    i += 1;
+                }
+            }
+            None => return None,
+        }
+        first = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal() {
+        assert!(glob_match("", ""));
+        assert!(glob_match("abc", "abc"));
+        assert!(!glob_match("abc", "abd"));
+        assert!(!glob_match("abc", "ab"));
+        assert!(!glob_match("ab", "abc"));
+        assert!(!glob_match("", "a"));
+    }
+
+    #[test]
+    fn star() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("*.txt", "notes.txt"));
+        assert!(glob_match("*.txt", ".txt"));
+        assert!(!glob_match("*.txt", "notes.txt.bak"));
+        assert!(glob_match("file:/*", "file:/home/notes"));
+        assert!(glob_match("a*b*c", "aXXbYYc"));
+        assert!(glob_match("a*b*c", "abbbc"));
+        assert!(!glob_match("a*b*c", "aXXcYYb"));
+        assert!(glob_match("**a", "ba"));
+        assert!(glob_match("a*", "a"));
+    }
+
+    #[test]
+    fn question() {
+        assert!(glob_match("?", "a"));
+        assert!(!glob_match("?", ""));
+        assert!(!glob_match("?", "ab"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(glob_match("*.?s", "main.rs"));
+        assert!(glob_match("?", "é"));
+    }
+
+    #[test]
+    fn classes() {
+        assert!(glob_match("[abc]", "b"));
+        assert!(!glob_match("[abc]", "d"));
+        assert!(glob_match("[a-z]1", "q1"));
+        assert!(!glob_match("[a-z]1", "Q1"));
+        assert!(glob_match("[!a-z]", "Q"));
+        assert!(glob_match("[^a-z]", "Q"));
+        assert!(!glob_match("[!a-z]", "q"));
+        assert!(glob_match("[]]", "]"));
+        assert!(glob_match("[a-]", "-"));
+        assert!(glob_match("*.[ch]", "main.h"));
+        assert!(!glob_match("*.[ch]", "main.rs"));
+    }
+
+    #[test]
+    fn unclosed_class() {
+        assert!(glob_match("[ab", "[ab"));
+        assert!(!glob_match("[ab", "a"));
+    }
+
+    #[test]
+    fn detection() {
+        assert!(is_glob("*.txt"));
+        assert!(is_glob("a?"));
+        assert!(is_glob("[ab]"));
+        assert!(!is_glob("plain"));
+    }
+}
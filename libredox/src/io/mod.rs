@@ -2,9 +2,10 @@
 
 use core::usize;
 use {fmt, str};
+use boxed::Box;
 use string::String;
 use vec::{IntoIter, Vec};
-use syscall::{sys_read, sys_write};
+use syscall::{sys_fpath, sys_fsync, sys_read, sys_write};
 
 pub struct Error;
 
@@ -61,6 +62,19 @@ pub trait Write {
     /// Write to the file
     fn write(&mut self, buf: &[u8]) -> Option<usize>;
 
+    /// Write all of the buffer, writing again after a short write
+    /// Returns `None` if a write fails or writes nothing
+    fn write_all(&mut self, buf: &[u8]) -> Option<usize> {
+        let mut written = 0;
+        while written < buf.len() {
+            match self.write(&buf[written ..]) {
+                Some(0) | None => return None,
+                Some(count) => written += count,
+            }
+        }
+        Some(written)
+    }
+
     /// Write a format to the file
     fn write_fmt(&mut self, args: fmt::Arguments) -> Result<(), Error> {
         match self.write(fmt::format(args).as_bytes()) {
@@ -93,18 +107,54 @@ pub fn stdin() -> Stdin {
 }
 
 impl Stdin {
+    /// Read a line, decoding it as UTF-8
+    /// A character split at the end of the read is completed with further reads
     pub fn read_line(&mut self, string: &mut String) -> Result<usize, Error> {
         let mut bytes = [0; 1024];
-        match self.read(&mut bytes) {
+        let mut vec = match self.read(&mut bytes) {
             None => return Err(Error),
-            Some(count) => {
-                for i in 0..count {
-                    string.push(bytes[i] as char); //TODO Allow UTF8
-                }
-                return Ok(count);
+            Some(count) => Vec::from(&bytes[.. count]),
+        };
+
+        while utf8_complete_len(&vec) < vec.len() {
+            let mut byte = [0; 1];
+            match self.read(&mut byte) {
+                Some(1) => vec.push(byte[0]),
+                _ => break,
             }
         }
+
+        string.push_str(&String::from_utf8_lossy(&vec));
+        Ok(vec.len())
+    }
+}
+
+/// The length of `bytes` without a UTF-8 sequence that is cut off at the end
+pub fn utf8_complete_len(bytes: &[u8]) -> usize {
+    for back in 1..5 {
+        if back > bytes.len() {
+            break;
+        }
+
+        let i = bytes.len() - back;
+        let b = bytes[i];
+        if b & 0xC0 != 0x80 {
+            // A lead byte, or ASCII
+            let len = if b & 0x80 == 0 {
+                1
+            } else if b & 0xE0 == 0xC0 {
+                2
+            } else if b & 0xF0 == 0xE0 {
+                3
+            } else if b & 0xF8 == 0xF0 {
+                4
+            } else {
+                1
+            };
+            return if back < len { i } else { bytes.len() };
+        }
     }
+    bytes.len()
 }
 
 /// Read implementation for standard input
@@ -121,6 +171,32 @@ impl Read for Stdin {
     }
 }
 
+/// The buffers capturing the standard output, the innermost last
+static mut captures: *mut Vec<Vec<u8>> = 0 as *mut Vec<Vec<u8>>;
+
+/// Capture what is written to the standard output, instead of writing it, until
+/// `end_capture`
+/// Captures nest, and only the innermost one receives the output
+pub fn begin_capture() {
+    unsafe {
+        if captures as usize == 0 {
+            captures = Box::into_raw(box Vec::new());
+        }
+        (*captures).push(Vec::new());
+    }
+}
+
+/// End the innermost capture, returning what was written to the standard output during it
+pub fn end_capture() -> Vec<u8> {
+    unsafe {
+        if captures as usize == 0 {
+            Vec::new()
+        } else {
+            (*captures).pop().unwrap_or(Vec::new())
+        }
+    }
+}
+
 /// Standard Output
 pub struct Stdout;
 
@@ -129,10 +205,38 @@ pub fn stdout() -> Stdout {
     Stdout
 }
 
+impl Stdout {
+    /// Get the canonical path of the standard output
+    pub fn path(&self) -> Option<String> {
+        unsafe {
+            let mut buf: [u8; 4096] = [0; 4096];
+            let count = sys_fpath(1, buf.as_mut_ptr(), buf.len());
+            if count == usize::MAX {
+                None
+            } else {
+                Some(String::from_utf8_unchecked(Vec::from(&buf[0..count])))
+            }
+        }
+    }
+
+    /// Flush the standard output, so everything written so far is shown
+    /// Returns false if the flush failed
+    pub fn flush(&mut self) -> bool {
+        unsafe { sys_fsync(1) != usize::MAX }
+    }
+}
+
 /// Write implementation for standard output
 impl Write for Stdout {
     fn write(&mut self, buf: &[u8]) -> Option<usize> {
         unsafe {
+            if captures as usize != 0 {
+                if let Some(capture) = (*captures).last_mut() {
+                    capture.push_all(buf);
+                    return Some(buf.len());
+                }
+            }
+
             let count = sys_write(1, buf.as_ptr(), buf.len());
             if count == usize::MAX {
                 None
@@ -169,3 +273,66 @@ impl Write for Stderr {
 pub fn _print(args: fmt::Arguments) {
     stdout().write_fmt(args);
 }
+
+#[cfg(test)]
+mod tests {
+    use core::cmp;
+    use super::*;
+
+    #[test]
+    fn nested_captures() {
+        begin_capture();
+        print!("outer ");
+        begin_capture();
+        print!("inner");
+        assert_eq!(end_capture(), b"inner");
+        println!("again");
+        assert_eq!(end_capture(), b"outer again\n");
+        assert!(end_capture().is_empty());
+    }
+
+    #[test]
+    fn utf8_complete() {
+        assert_eq!(utf8_complete_len(b""), 0);
+        assert_eq!(utf8_complete_len(b"abc"), 3);
+        // é is C3 A9
+        assert_eq!(utf8_complete_len(b"ab\xC3\xA9"), 4);
+        assert_eq!(utf8_complete_len(b"ab\xC3"), 2);
+        // € is E2 82 AC
+        assert_eq!(utf8_complete_len(b"\xE2"), 0);
+        assert_eq!(utf8_complete_len(b"\xE2\x82"), 0);
+        assert_eq!(utf8_complete_len(b"\xE2\x82\xAC"), 3);
+        // 😀 is F0 9F 98 80
+        assert_eq!(utf8_complete_len(b"x\xF0\x9F\x98"), 1);
+        assert_eq!(utf8_complete_len(b"x\xF0\x9F\x98\x80"), 5);
+        // Stray continuation bytes are left to the decoder
+        assert_eq!(utf8_complete_len(b"\x80\x80\x80\x80"), 4);
+    }
+
+    /// Writes at most three bytes at a time, and fails once `limit` bytes are written
+    struct ShortWriter {
+        written: Vec<u8>,
+        limit: usize,
+    }
+
+    impl Write for ShortWriter {
+        fn write(&mut self, buf: &[u8]) -> Option<usize> {
+            if self.written.len() >= self.limit {
+                return None;
+            }
+            let count = cmp::min(cmp::min(buf.len(), 3), self.limit - self.written.len());
+            self.written.extend(buf[.. count].iter().cloned());
+            Some(count)
+        }
+    }
+
+    #[test]
+    fn short_writes() {
+        let mut writer = ShortWriter { written: Vec::new(), limit: 100 };
+        assert_eq!(writer.write_all(b"hello, world"), Some(12));
+        assert_eq!(writer.written, b"hello, world");
+
+        let mut failing = ShortWriter { written: Vec::new(), limit: 4 };
+        assert_eq!(failing.write_all(b"hello"), None);
+    }
+}
@@ -207,5 +207,6 @@
     pub mod url;
 
     pub mod get_slice;
+    pub mod glob;
     pub mod to_num;
 /* } Additional Stuff */
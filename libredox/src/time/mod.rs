@@ -1,5 +1,10 @@
 //! A module for time
+//!
+//! Both clocks come from `sys_clock_gettime`, which the kernel advances on every PIT tick, so
+//! their resolution is about 2.25 milliseconds. Code that needs the time should go through a
+//! `Clock`, so that it can be tested with a `FakeClock`.
 
+use core::cell::Cell;
 use core::cmp::{Ordering, PartialEq};
 use core::ops::{Add, Sub};
 
@@ -59,6 +64,15 @@ impl Duration {
         Duration::new(tp.tv_sec, tp.tv_nsec)
     }
 
+    /// Get the duration in whole milliseconds, zero if it is negative
+    pub fn to_millis(&self) -> u64 {
+        if self.secs < 0 {
+            0
+        } else {
+            self.secs as u64 * 1000 + (self.nanos / NANOS_PER_MILLI) as u64
+        }
+    }
+
     /// Sleep the duration
     pub fn sleep(&self) -> Duration {
         let req = TimeSpec {
@@ -77,6 +91,76 @@ impl Duration {
     }
 }
 
+/// A source of time
+pub trait Clock {
+    /// Milliseconds since boot, which never go backwards
+    fn monotonic_ms(&self) -> u64;
+
+    /// The wall clock time since the Unix epoch
+    fn realtime(&self) -> Duration;
+}
+
+/// The kernel clocks
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn monotonic_ms(&self) -> u64 {
+        Duration::monotonic().to_millis()
+    }
+
+    fn realtime(&self) -> Duration {
+        Duration::realtime()
+    }
+}
+
+/// A clock that only moves when it is told to, for testing timing logic
+pub struct FakeClock {
+    monotonic_ms: Cell<u64>,
+    realtime: Cell<Duration>,
+}
+
+impl FakeClock {
+    /// Create a fake clock, stopped at boot and the Unix epoch
+    pub fn new() -> Self {
+        FakeClock {
+            monotonic_ms: Cell::new(0),
+            realtime: Cell::new(Duration::new(0, 0)),
+        }
+    }
+
+    /// Move both clocks forward
+    pub fn advance(&self, ms: u64) {
+        self.monotonic_ms.set(self.monotonic_ms.get() + ms);
+        self.realtime.set(self.realtime.get() + Duration::new((ms / 1000) as i64,
+                                                              (ms % 1000) as i32 * NANOS_PER_MILLI));
+    }
+
+    /// Set the wall clock
+    pub fn set_realtime(&self, realtime: Duration) {
+        self.realtime.set(realtime);
+    }
+}
+
+impl Clock for FakeClock {
+    fn monotonic_ms(&self) -> u64 {
+        self.monotonic_ms.get()
+    }
+
+    fn realtime(&self) -> Duration {
+        self.realtime.get()
+    }
+}
+
+/// Milliseconds since boot on the system clock
+pub fn monotonic_ms() -> u64 {
+    SystemClock.monotonic_ms()
+}
+
+/// The wall clock time on the system clock
+pub fn realtime() -> Duration {
+    SystemClock.realtime()
+}
+
 impl Add for Duration {
     type Output = Duration;
 
@@ -116,3 +200,24 @@ impl PartialOrd for Duration {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duration_millis() {
+        assert_eq!(Duration::new(2, 500 * NANOS_PER_MILLI).to_millis(), 2500);
+        assert_eq!(Duration::new(0, 999_999).to_millis(), 0);
+        assert_eq!(Duration::new(-1, 0).to_millis(), 0);
+    }
+
+    #[test]
+    fn fake_clock() {
+        let clock = FakeClock::new();
+        clock.set_realtime(Duration::new(100, 0));
+        clock.advance(1500);
+        assert_eq!(clock.monotonic_ms(), 1500);
+        assert!(clock.realtime() == Duration::new(101, 500 * NANOS_PER_MILLI));
+    }
+}